@@ -0,0 +1,53 @@
+/**
+ * $File: flx.h $
+ * $Date: 2026-10-16 01:22:37 $
+ * $Revision: $
+ * $Creator: Jen-Chieh Shen $
+ * $Notice: See LICENSE.txt for modification and distribution information
+ *                   Copyright © 2026 by Shen, Jen-Chieh $
+ */
+/*
+ * C interface of flx-rs, available with the `capi` feature. Build a
+ * linkable library with
+ *
+ *     cargo rustc --release --features capi --crate-type cdylib
+ *
+ * or `--crate-type staticlib`.
+ */
+#ifndef FLX_H
+#define FLX_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Match result, allocated by `flx_score` and released with
+ * `flx_free_result`. */
+typedef struct FlxResult {
+    /* Score of the match; higher is better. */
+    int64_t score;
+    /* Character indices of the matched characters, ascending. */
+    int32_t *indices;
+    /* Number of entries in `indices`. */
+    size_t indices_len;
+    /* Number of matched characters that directly follow the first one,
+     * i.e. the length of the leading contiguous run minus one. */
+    int32_t tail;
+} FlxResult;
+
+/* Return the best match of QUERY against CANDIDATE, both NUL-terminated
+ * UTF-8, or NULL when it doesn't match or either string is NULL or not
+ * valid UTF-8. */
+FlxResult *flx_score(const char *candidate, const char *query);
+
+/* Release RESULT, as returned by `flx_score`. NULL is ignored. */
+void flx_free_result(FlxResult *result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* FLX_H */
@@ -0,0 +1,135 @@
+/**
+ * $File: cache.rs $
+ * $Date: 2026-10-16 00:49:08 $
+ * $Revision: $
+ * $Creator: Jen-Chieh Shen $
+ * $Notice: See LICENSE.txt for modification and distribution information
+ *                   Copyright © 2026 by Shen, Jen-Chieh $
+ */
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+use query::Query;
+use search::{score_query, Result};
+
+/// Memo of scores keyed by candidate ID and compiled query.
+///
+/// UIs re-render the same visible rows many times while the query stays
+/// put; looking those up here skips rescoring them. Entries expire after
+/// the TTL, if one is set, and the oldest entry is evicted once the cache
+/// holds CAPACITY entries.
+#[derive(Debug, Clone)]
+pub struct ScoreCache<K> {
+    /// Result, scoring time and insertion number of each entry.
+    entries: HashMap<(K, u64), (Option<Result>, Instant, u64)>,
+    /// Keys with their insertion number, oldest first. A key scored again
+    /// leaves its earlier record behind, which no longer matches.
+    order: VecDeque<((K, u64), u64)>,
+    inserted: u64,
+    capacity: usize,
+    ttl: Option<Duration>,
+}
+
+impl<K: Hash + Eq + Clone> ScoreCache<K> {
+    /// Create an empty cache holding at most CAPACITY entries.
+    pub fn new(capacity: usize) -> ScoreCache<K> {
+        return ScoreCache {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            inserted: 0,
+            capacity,
+            ttl: None,
+        };
+    }
+
+    /// Expire entries TTL after they were scored.
+    pub fn ttl(mut self, ttl: Duration) -> ScoreCache<K> {
+        self.ttl = Some(ttl);
+        return self;
+    }
+
+    /// Return the result of CANDIDATE, known as ID, against QUERY.
+    ///
+    /// The candidate is only scored when no fresh entry exists for ID and
+    /// QUERY, so ID must always name the same candidate string.
+    pub fn score(&mut self, id: K, candidate: &str, query: &Query) -> Option<Result> {
+        let now: Instant = Instant::now();
+        let key: (K, u64) = (id, query_hash(query));
+        if let Some((result, scored, _)) = self.entries.get(&key) {
+            if !self.expired(*scored, now) {
+                return result.clone();
+            }
+        }
+
+        let result: Option<Result> = score_query(candidate, query);
+        if self.capacity == 0 {
+            return result;
+        }
+        if self.capacity <= self.entries.len() && !self.entries.contains_key(&key) {
+            self.evict(now);
+        }
+        self.inserted += 1;
+        self.order.push_back((key.clone(), self.inserted));
+        self.entries
+            .insert(key, (result.clone(), now, self.inserted));
+        // Drop the records left behind once they outnumber the entries
+        if 2 * self.entries.len() < self.order.len() {
+            let entries = &self.entries;
+            self.order.retain(|(key, inserted)| {
+                entries.get(key).is_some_and(|entry| entry.2 == *inserted)
+            });
+        }
+        return result;
+    }
+
+    /// Number of cached entries, expired ones included.
+    pub fn len(&self) -> usize {
+        return self.entries.len();
+    }
+
+    /// Check if the cache holds no entries.
+    pub fn is_empty(&self) -> bool {
+        return self.entries.is_empty();
+    }
+
+    /// Drop every entry.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    /// Check if an entry scored at SCORED is stale at NOW.
+    fn expired(&self, scored: Instant, now: Instant) -> bool {
+        return self
+            .ttl
+            .is_some_and(|ttl| ttl <= now.duration_since(scored));
+    }
+
+    /// Make room for one entry: drop the expired ones, or else the oldest.
+    ///
+    /// Entries are scored in insertion order, so the expired ones come
+    /// first in `order`, and each record is popped at most once.
+    fn evict(&mut self, now: Instant) {
+        while let Some((key, inserted)) = self.order.pop_front() {
+            let scored: Instant = match self.entries.get(&key) {
+                Some(&(_, scored, current)) if current == inserted => scored,
+                _ => continue,
+            };
+            if self.expired(scored, now) || self.capacity <= self.entries.len() {
+                self.entries.remove(&key);
+                continue;
+            }
+            self.order.push_front((key, inserted));
+            break;
+        }
+    }
+}
+
+/// Return a hash identifying the compiled QUERY.
+fn query_hash(query: &Query) -> u64 {
+    let mut hasher: DefaultHasher = DefaultHasher::new();
+    query.hash(&mut hasher);
+    return hasher.finish();
+}
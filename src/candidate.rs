@@ -0,0 +1,75 @@
+/**
+ * $File: candidate.rs $
+ * $Date: 2026-10-16 01:17:22 $
+ * $Revision: $
+ * $Creator: Jen-Chieh Shen $
+ * $Notice: See LICENSE.txt for modification and distribution information
+ *                   Copyright © 2026 by Shen, Jen-Chieh $
+ */
+use std::sync::OnceLock;
+
+use query::{CaseMode, Query};
+use search::{score_query, Prepared, Result, ScoreOptions, StrInfo};
+
+/// A candidate string with its heatmap and occurrence tables kept around.
+///
+/// The heatmap is built once, for the options given at creation; each
+/// occurrence table is built the first time a query needs its case mode.
+/// For a candidate set that stays put while the query changes, this skips
+/// rebuilding both on every keystroke.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    text: String,
+    options: ScoreOptions,
+    prepared: Prepared<'static>,
+    /// Occurrence tables for `CaseMode::Mixed`, `CaseMode::Insensitive`
+    /// and `CaseMode::Sensitive`.
+    tables: [OnceLock<StrInfo>; 3],
+}
+
+impl Candidate {
+    /// Prepare TEXT for scoring with the default options.
+    pub fn new(text: &str) -> Candidate {
+        return Candidate::with_options(text, &ScoreOptions::default());
+    }
+
+    /// Prepare TEXT for scoring with OPTIONS.
+    pub fn with_options(text: &str, options: &ScoreOptions) -> Candidate {
+        return Candidate {
+            text: text.to_string(),
+            options: options.clone(),
+            prepared: Prepared::new(text, options).into_owned(),
+            tables: Default::default(),
+        };
+    }
+
+    /// Return the candidate string.
+    pub fn text(&self) -> &str {
+        return &self.text;
+    }
+
+    /// Return the options the candidate was prepared with.
+    pub fn options(&self) -> &ScoreOptions {
+        return &self.options;
+    }
+
+    /// Return the best match of QUERY, as `score_query` would.
+    ///
+    /// A query with other options than the candidate's is scored from
+    /// scratch.
+    pub fn score(&self, query: &Query) -> Option<Result> {
+        if query.options != self.options {
+            return score_query(&self.text, query);
+        }
+        let case_mode: CaseMode = query.case_mode.resolve(query.text());
+        let slot: usize = match case_mode {
+            // `resolve` never returns `CaseMode::Smart`
+            CaseMode::Mixed | CaseMode::Smart => 0,
+            CaseMode::Insensitive => 1,
+            CaseMode::Sensitive => 2,
+        };
+        let str_info: &StrInfo =
+            self.tables[slot].get_or_init(|| self.prepared.str_info(case_mode));
+        return self.prepared.score(query, str_info).unwrap_or(None);
+    }
+}
@@ -0,0 +1,121 @@
+/**
+ * $File: capi.rs $
+ * $Date: 2026-10-16 01:22:37 $
+ * $Revision: $
+ * $Creator: Jen-Chieh Shen $
+ * $Notice: See LICENSE.txt for modification and distribution information
+ *                   Copyright © 2026 by Shen, Jen-Chieh $
+ */
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::ptr;
+
+use search::{score, Result};
+
+/// Match result handed across the C ABI, declared in `include/flx.h`.
+///
+/// Allocated by `flx_score` and released with `flx_free_result`; the
+/// fields must not be changed in between.
+#[repr(C)]
+#[derive(Debug)]
+pub struct FlxResult {
+    /// Score of the match; higher is better.
+    pub score: i64,
+    /// Character indices of the matched characters, ascending.
+    pub indices: *mut i32,
+    /// Number of entries in `indices`.
+    pub indices_len: usize,
+    /// Number of matched characters that directly follow the first one,
+    /// i.e. the length of the leading contiguous run minus one.
+    pub tail: i32,
+}
+
+/// Return the best match of QUERY against CANDIDATE, or null when it
+/// doesn't match or either string is null or not valid UTF-8.
+///
+/// # Safety
+///
+/// CANDIDATE and QUERY must be null or point to NUL-terminated strings
+/// that stay valid for the duration of the call. A non-null result must be
+/// released with `flx_free_result`.
+#[no_mangle]
+pub unsafe extern "C" fn flx_score(
+    candidate: *const c_char,
+    query: *const c_char,
+) -> *mut FlxResult {
+    if candidate.is_null() || query.is_null() {
+        return ptr::null_mut();
+    }
+    let (candidate, query) = match (
+        CStr::from_ptr(candidate).to_str(),
+        CStr::from_ptr(query).to_str(),
+    ) {
+        (Ok(candidate), Ok(query)) => (candidate, query),
+        _ => return ptr::null_mut(),
+    };
+    let result: Result = match score(candidate, query) {
+        Some(result) => result,
+        None => return ptr::null_mut(),
+    };
+    let indices: Box<[i32]> = result.indices.into_boxed_slice();
+    let indices_len: usize = indices.len();
+    return Box::into_raw(Box::new(FlxResult {
+        score: result.score.get(),
+        indices: Box::into_raw(indices) as *mut i32,
+        indices_len,
+        tail: result.tail,
+    }));
+}
+
+/// Release RESULT, as returned by `flx_score`. Null is ignored.
+///
+/// # Safety
+///
+/// RESULT must be null or come from `flx_score`, and must not be used or
+/// released again afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn flx_free_result(result: *mut FlxResult) {
+    if result.is_null() {
+        return;
+    }
+    let result: Box<FlxResult> = Box::from_raw(result);
+    drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+        result.indices,
+        result.indices_len,
+    )));
+}
+
+#[cfg(all(test, feature = "capi"))]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    use std::slice;
+
+    #[test]
+    fn score_round_trip() {
+        let candidate: CString = CString::new("switch-to-buffer").unwrap();
+        let query: CString = CString::new("stb").unwrap();
+        let invalid: &[u8] = b"\xff\xfe\0";
+        let unmatched: CString = CString::new("xyz").unwrap();
+        unsafe {
+            assert!(flx_score(ptr::null(), query.as_ptr()).is_null());
+            assert!(flx_score(candidate.as_ptr(), ptr::null()).is_null());
+            assert!(flx_score(invalid.as_ptr() as *const c_char, query.as_ptr()).is_null());
+            assert!(flx_score(candidate.as_ptr(), invalid.as_ptr() as *const c_char).is_null());
+            assert!(flx_score(candidate.as_ptr(), unmatched.as_ptr()).is_null());
+
+            let expected: Result = score("switch-to-buffer", "stb").unwrap();
+            let result: *mut FlxResult = flx_score(candidate.as_ptr(), query.as_ptr());
+            assert!(!result.is_null());
+            let flx_result: &FlxResult = &*result;
+            assert_eq!(flx_result.score, expected.score.get());
+            assert_eq!(
+                slice::from_raw_parts(flx_result.indices, flx_result.indices_len),
+                expected.indices.as_slice()
+            );
+            assert_eq!(flx_result.tail, expected.tail);
+            flx_free_result(result);
+            flx_free_result(ptr::null_mut());
+        }
+    }
+}
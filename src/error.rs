@@ -0,0 +1,39 @@
+/**
+ * $File: error.rs $
+ * $Date: 2026-10-16 00:34:13 $
+ * $Revision: $
+ * $Creator: Jen-Chieh Shen $
+ * $Notice: See LICENSE.txt for modification and distribution information
+ *                   Copyright © 2026 by Shen, Jen-Chieh $
+ */
+use std::error::Error;
+use std::fmt;
+
+/// Reasons a fallible entry point rejects its input.
+///
+/// A candidate that simply doesn't match is not an error; those calls
+/// return `Ok(None)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FlxError {
+    /// The query can't be matched against anything.
+    InvalidQuery(String),
+    /// The candidate has more characters than the search can index for
+    /// the given query.
+    CandidateTooLong { length: usize, limit: usize },
+}
+
+impl fmt::Display for FlxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FlxError::InvalidQuery(reason) => write!(f, "invalid query: {}", reason),
+            FlxError::CandidateTooLong { length, limit } => write!(
+                f,
+                "candidate is too long: {} characters, limit is {}",
+                length, limit
+            ),
+        }
+    }
+}
+
+impl Error for FlxError {}
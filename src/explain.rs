@@ -0,0 +1,349 @@
+/**
+ * $File: explain.rs $
+ * $Date: 2026-10-16 00:53:05 $
+ * $Revision: $
+ * $Creator: Jen-Chieh Shen $
+ * $Notice: See LICENSE.txt for modification and distribution information
+ *                   Copyright © 2026 by Shen, Jen-Chieh $
+ */
+use std::cmp::{Ordering, Reverse};
+
+use query::{CaseMode, Query};
+use search::{build_heatmap, Cell, Prepared, Result, Segmentation, StrInfo};
+use value::Score;
+
+/// Number of `Component` variants.
+const COMPONENT_COUNT: usize = 17;
+
+/// A part of the score, named after the rule that adds it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Component {
+    /// `DEFAULT_SCORE` of every matched character.
+    Base,
+    /// Bonus of the last character.
+    FinalChar,
+    /// Penalty of the character after a `.`.
+    Extension,
+    /// Penalty for the number of groups.
+    GroupCount,
+    /// Bonus of the basepath, with its separator boosts and word penalty.
+    Basepath,
+    /// Penalty of the groups outside the basepath.
+    NonBasepath,
+    /// Bonus of characters that start a word.
+    WordStart,
+    /// Penalty of words late in their group.
+    WordOrder,
+    /// Penalty of characters late in their word.
+    CharOrder,
+    /// Bonus of contiguous runs of matched characters.
+    Contiguity,
+    /// `FULL_MATCH_BOOST` for short queries matching the whole string.
+    FullMatch,
+    /// `ScoreOptions::prefix_bonus`.
+    PrefixTier,
+    /// `ScoreOptions::initialism_bonus`.
+    Initialism,
+    /// `ScoreOptions::component_initials_bonus`.
+    ComponentInitials,
+    /// `ScoreOptions::hidden_penalty`.
+    Hidden,
+    /// `ScoreOptions::depth_penalty`.
+    Depth,
+    /// `ScoreOptions::extension_boosts`.
+    ExtensionBoost,
+}
+
+impl Component {
+    /// Every component, in the order scoring applies them.
+    pub const ALL: [Component; COMPONENT_COUNT] = [
+        Component::Base,
+        Component::FinalChar,
+        Component::Extension,
+        Component::GroupCount,
+        Component::Basepath,
+        Component::NonBasepath,
+        Component::WordStart,
+        Component::WordOrder,
+        Component::CharOrder,
+        Component::Contiguity,
+        Component::FullMatch,
+        Component::PrefixTier,
+        Component::Initialism,
+        Component::ComponentInitials,
+        Component::Hidden,
+        Component::Depth,
+        Component::ExtensionBoost,
+    ];
+}
+
+/// A heatmap entry split by component.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Parts([i32; COMPONENT_COUNT]);
+
+impl Cell for Parts {
+    fn new(component: Component, amount: i32) -> Parts {
+        let mut parts: Parts = Parts::default();
+        parts.add(component, amount);
+        return parts;
+    }
+
+    fn add(&mut self, component: Component, amount: i32) {
+        let part: &mut i32 = &mut self.0[component as usize];
+        *part = part.saturating_add(amount);
+    }
+}
+
+/// A match whose score is split into the components that make it up.
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    /// The match, as `score_query` returns it.
+    pub result: Result,
+    /// The non-zero components, in `Component::ALL` order. They add up to
+    /// the score.
+    pub parts: Vec<(Component, i32)>,
+}
+
+impl Explanation {
+    /// Return how much COMPONENT contributes to the score.
+    pub fn get(&self, component: Component) -> i32 {
+        return self
+            .parts
+            .iter()
+            .find(|part| part.0 == component)
+            .map_or(0, |part| part.1);
+    }
+}
+
+/// Match QUERY against STR and split the score into its components.
+///
+/// Returns `None` where `score_query` would.
+pub fn explain(str: &str, query: &Query) -> Option<Explanation> {
+    let prepared: Prepared = Prepared::new(str, &query.options);
+    let str_info: StrInfo = prepared.str_info(query.case_mode.resolve(query.text()));
+    let mut adjustments: Vec<(Component, i32)> = Vec::new();
+    let result: Result = prepared
+        .score_parts(query, &str_info, Some(&mut adjustments))
+        .unwrap_or(None)?;
+
+    let (cells, _) = build_heatmap::<Parts>(prepared.text(), &query.options);
+    let mut totals: [i32; COMPONENT_COUNT] = [0; COMPONENT_COUNT];
+    for &index in &result.indices {
+        let position: usize = prepared.position(index as usize);
+        for (total, part) in totals.iter_mut().zip(cells[position].0) {
+            *total = total.saturating_add(part);
+        }
+    }
+    // Whatever the heatmap and the adjustments don't cover was earned by
+    // contiguous runs during the search
+    let heat: i64 = totals.iter().map(|&part| part as i64).sum();
+    let adjusted: i64 = adjustments.iter().map(|part| part.1 as i64).sum();
+    let contiguity: i64 = result.score.get() - heat - adjusted;
+    totals[Component::Contiguity as usize] =
+        contiguity.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+    for (component, amount) in adjustments {
+        totals[component as usize] = totals[component as usize].saturating_add(amount);
+    }
+
+    let parts: Vec<(Component, i32)> = Component::ALL
+        .iter()
+        .zip(totals)
+        .filter(|part| part.1 != 0)
+        .map(|(&component, amount)| (component, amount))
+        .collect();
+    return Some(Explanation { result, parts });
+}
+
+/// How two candidates compare under the same query.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    /// Explanation of the first candidate, if it matches.
+    pub a: Option<Explanation>,
+    /// Explanation of the second candidate, if it matches.
+    pub b: Option<Explanation>,
+    /// Components where the candidates differ, as A minus B, the largest
+    /// difference first.
+    pub differences: Vec<(Component, i32)>,
+}
+
+impl Comparison {
+    /// Return how A ranks against B: `Greater` if A ranks higher. A
+    /// candidate that matches ranks higher than one that doesn't.
+    pub fn ordering(&self) -> Ordering {
+        let score = |explanation: &Option<Explanation>| {
+            explanation
+                .as_ref()
+                .map(|explanation| explanation.result.score)
+        };
+        return score(&self.a).cmp(&score(&self.b));
+    }
+}
+
+/// Compare CANDIDATE-A with CANDIDATE-B under QUERY, component by
+/// component, to tell why one outranks the other.
+pub fn compare(candidate_a: &str, candidate_b: &str, query: &Query) -> Comparison {
+    let a: Option<Explanation> = explain(candidate_a, query);
+    let b: Option<Explanation> = explain(candidate_b, query);
+    let get = |explanation: &Option<Explanation>, component: Component| {
+        explanation
+            .as_ref()
+            .map_or(0, |explanation| explanation.get(component))
+    };
+    let mut differences: Vec<(Component, i32)> = Component::ALL
+        .iter()
+        .map(|&component| (component, get(&a, component) - get(&b, component)))
+        .filter(|difference| difference.1 != 0)
+        .collect();
+    differences.sort_by_key(|difference| Reverse(difference.1.abs()));
+    return Comparison { a, b, differences };
+}
+
+/// Features of a match, for re-ranking it with an external model.
+///
+/// Positions count the characters of the string as matched, after the
+/// `normalize_path` and `skip_invisible` options have applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchFeatures {
+    /// The score of the match.
+    pub score: Score,
+    /// Number of matched characters.
+    pub matched: usize,
+    /// Length of the longest run of contiguous matched characters.
+    pub longest_run: usize,
+    /// Share of matched characters right after the previous one, from 0
+    /// to 1. A single character counts as contiguous.
+    pub contiguity: f32,
+    /// Number of matched characters that start a word.
+    pub boundary_hits: usize,
+    /// Number of unmatched characters between each pair of consecutive
+    /// matched characters that aren't adjacent.
+    pub gaps: Vec<usize>,
+    /// Index of the first matched character.
+    pub first_index: usize,
+    /// Share of matched characters inside the basepath, from 0 to 1.
+    pub basepath_coverage: f32,
+    /// Query length over string length, from 0 to 1.
+    pub length_ratio: f32,
+}
+
+/// Match QUERY against STR and describe the match as `MatchFeatures`.
+///
+/// Returns `None` where `score_query` would.
+pub fn features(str: &str, query: &Query) -> Option<MatchFeatures> {
+    let prepared: Prepared = Prepared::new(str, &query.options);
+    let str_info: StrInfo = prepared.str_info(query.case_mode.resolve(query.text()));
+    let result: Result = prepared.score(query, &str_info).unwrap_or(None)?;
+    let segmentation: &Segmentation = prepared.segmentation();
+
+    let indices: Vec<usize> = result
+        .indices
+        .iter()
+        .map(|&index| prepared.position(index as usize))
+        .collect();
+    let matched: usize = indices.len();
+    let gaps: Vec<usize> = indices
+        .windows(2)
+        .map(|pair| pair[1] - pair[0] - 1)
+        .filter(|&gap| gap != 0)
+        .collect();
+    let contiguity: f32 = if matched < 2 {
+        1.0
+    } else {
+        (matched - 1 - gaps.len()) as f32 / (matched - 1) as f32
+    };
+    let boundary_hits: usize = indices
+        .iter()
+        .filter(|index| segmentation.word_starts.binary_search(index).is_ok())
+        .count();
+    let in_basepath: usize = segmentation.basepath.as_ref().map_or(0, |basepath| {
+        indices
+            .iter()
+            .filter(|index| basepath.contains(index))
+            .count()
+    });
+    let length: usize = prepared.text().chars().count();
+    return Some(MatchFeatures {
+        score: result.score,
+        matched,
+        longest_run: result.longest_run as usize,
+        contiguity,
+        boundary_hits,
+        gaps,
+        first_index: indices[0],
+        basepath_coverage: in_basepath as f32 / matched as f32,
+        length_ratio: matched as f32 / length as f32,
+    });
+}
+
+/// Return up to LIMIT alignments of QUERY in STR, each scored as
+/// `score_query` scores it when it picks that alignment, filename-first
+/// and component-initials adjustments included.
+///
+/// Every way to pick the query characters in order is listed, in
+/// lexicographic order of the indices, whether or not the search would
+/// pick it. This is meant for checking the search and for looking at
+/// near misses; the count grows quickly with repeated characters.
+pub fn alignments(str: &str, query: &Query, limit: usize) -> Vec<Result> {
+    let prepared: Prepared = Prepared::new(str, &query.options);
+    let mut str_info: StrInfo = prepared.str_info(query.case_mode.resolve(query.text()));
+    if query.word_starts_only {
+        let word_starts: &[usize] = &prepared.segmentation().word_starts;
+        str_info.retain(|index| word_starts.binary_search(&index).is_ok());
+    }
+    let text: String = if query.case_mode == CaseMode::Insensitive {
+        query.text().to_lowercase()
+    } else {
+        query.text().to_string()
+    };
+    let needles: Vec<Vec<usize>> = text
+        .chars()
+        .map(|ch| str_info.occurrences(ch).collect())
+        .collect();
+    if needles.is_empty() {
+        return Vec::new();
+    }
+
+    let mut found: Vec<Vec<usize>> = Vec::new();
+    collect_alignments(&needles, query.max_gap, &mut Vec::new(), &mut found, limit);
+    return found
+        .into_iter()
+        .map(|indices| prepared.score_alignment(&indices, &query.options))
+        .collect();
+}
+
+/// Extend the partial alignment INDICES with every occurrence of the next
+/// query character, pushing complete ones to FOUND until it holds LIMIT.
+fn collect_alignments(
+    needles: &[Vec<usize>],
+    max_gap: Option<u32>,
+    indices: &mut Vec<usize>,
+    found: &mut Vec<Vec<usize>>,
+    limit: usize,
+) {
+    if found.len() == limit {
+        return;
+    }
+    let needle: &[usize] = match needles.get(indices.len()) {
+        Some(needle) => needle,
+        None => {
+            found.push(indices.clone());
+            return;
+        }
+    };
+    for &index in needle {
+        if let Some(&last) = indices.last() {
+            if index <= last {
+                continue;
+            }
+            if max_gap.is_some_and(|gap| last + 1 + (gap as usize) < index) {
+                break;
+            }
+        }
+        indices.push(index);
+        collect_alignments(needles, max_gap, indices, found, limit);
+        indices.pop();
+        if found.len() == limit {
+            return;
+        }
+    }
+}
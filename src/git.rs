@@ -0,0 +1,43 @@
+/**
+ * $File: git.rs $
+ * $Date: 2026-10-16 00:48:19 $
+ * $Revision: $
+ * $Creator: Jen-Chieh Shen $
+ * $Notice: See LICENSE.txt for modification and distribution information
+ *                   Copyright © 2026 by Shen, Jen-Chieh $
+ */
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// Return the files git tracks in the repository at DIR.
+///
+/// Paths are relative to DIR, ready to be ranked as candidates. This runs
+/// `git ls-files -z`, so git must be on the `PATH`.
+///
+/// # Arguments
+///
+/// * `dir` - Any directory inside the work tree.
+pub fn git_tracked_files(dir: &Path) -> io::Result<Vec<String>> {
+    let output = Command::new("git")
+        .arg("ls-files")
+        .arg("-z")
+        .current_dir(dir)
+        .output()?;
+    if !output.status.success() {
+        let message: String = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(io::Error::other(message));
+    }
+    return Ok(parse_ls_files(&output.stdout));
+}
+
+/// Split the NUL-separated OUTPUT of `git ls-files -z` into paths.
+///
+/// Paths that are not valid UTF-8 are converted lossily.
+pub fn parse_ls_files(output: &[u8]) -> Vec<String> {
+    return output
+        .split(|&byte| byte == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| String::from_utf8_lossy(path).into_owned())
+        .collect();
+}
@@ -0,0 +1,54 @@
+/**
+ * $File: highlight.rs $
+ * $Date: 2026-10-16 00:55:13 $
+ * $Revision: $
+ * $Creator: Jen-Chieh Shen $
+ * $Notice: See LICENSE.txt for modification and distribution information
+ *                   Copyright © 2026 by Shen, Jen-Chieh $
+ */
+use std::cmp::min;
+use std::ops::Range;
+
+use search::Result;
+
+/// A run of characters of a candidate, matched or not.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Segment {
+    /// Character range of the run.
+    pub range: Range<usize>,
+    /// Whether the run consists of matched characters.
+    pub matched: bool,
+}
+
+/// Split CANDIDATE into alternating matched and unmatched runs of RESULT.
+///
+/// The segments cover the whole candidate in order, which is what list
+/// renderers need to draw a highlighted row.
+pub fn segments(candidate: &str, result: &Result) -> Vec<Segment> {
+    let len: usize = candidate.chars().count();
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut end: usize = 0;
+    for range in result.ranges() {
+        if len <= range.start {
+            break;
+        }
+        if end < range.start {
+            segments.push(Segment {
+                range: end..range.start,
+                matched: false,
+            });
+        }
+        end = min(range.end, len);
+        segments.push(Segment {
+            range: range.start..end,
+            matched: true,
+        });
+    }
+    if end < len {
+        segments.push(Segment {
+            range: end..len,
+            matched: false,
+        });
+    }
+    return segments;
+}
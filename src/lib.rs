@@ -6,6 +6,54 @@
  * $Notice: See LICENSE.txt for modification and distribution information
  *                   Copyright © 2021 by Shen, Jen-Chieh $
  */
+mod cache;
+mod candidate;
+#[cfg(feature = "capi")]
+mod capi;
+mod error;
+mod explain;
+#[cfg(feature = "git")]
+mod git;
+mod highlight;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod path_symbol;
+mod preset;
+mod query;
+mod rank;
 mod search;
+mod session;
+mod tokens;
+mod tuning;
+mod value;
 
-pub use search::{find_best_match, get_heatmap_str, score, Result};
+pub use cache::ScoreCache;
+pub use candidate::Candidate;
+#[cfg(feature = "capi")]
+pub use capi::{flx_free_result, flx_score, FlxResult};
+pub use error::FlxError;
+pub use explain::{
+    alignments, compare, explain, features, Comparison, Component, Explanation, MatchFeatures,
+};
+#[cfg(feature = "git")]
+pub use git::{git_tracked_files, parse_ls_files};
+pub use highlight::{segments, Segment};
+#[cfg(feature = "parallel")]
+pub use parallel::{rank_par, rank_par_query};
+pub use path_symbol::{score_path_symbol, PathSymbolResult};
+pub use preset::Preset;
+pub use query::{CaseMode, Query, QuerySet, Tiebreak};
+pub use rank::{
+    dedup_items, merge_items, rank_items, rank_items_by_directory, rank_items_by_tag,
+    rank_items_prioritized, rank_items_query, rank_page, rank_top_n, Page, RankedGroup,
+};
+pub use search::{
+    basepath, best_match, consts, find_best_match, get_heatmap_str, normalize_path, score,
+    score_many, score_many_query, score_n_best, score_query, score_with_options, try_score,
+    try_score_query, BasepathGroup, Heatmap, MatchKind, Result, ScoreOptions, Segmentation,
+    StrInfo,
+};
+pub use session::Session;
+pub use tokens::{score_tokens, score_tokens_query, TokenResult};
+pub use tuning::{compare_options, tune, OptionsDiff, Preference, QueryDiff, Tunable, Tuned};
+pub use value::Score;
@@ -0,0 +1,108 @@
+/**
+ * $File: parallel.rs $
+ * $Date: 2026-10-16 01:16:21 $
+ * $Revision: $
+ * $Creator: Jen-Chieh Shen $
+ * $Notice: See LICENSE.txt for modification and distribution information
+ *                   Copyright © 2026 by Shen, Jen-Chieh $
+ */
+use std::thread;
+
+use query::Query;
+use rank::sort_ranked;
+use search::{score_many_query, Result};
+
+/// Rank CANDIDATES against QUERY on every available core.
+///
+/// Returns the index and result of each matching candidate, sorted by
+/// descending score; equal scores are ordered by the query's `Tiebreak`
+/// and then input order, so the ranking does not depend on the number of
+/// threads.
+///
+/// # Arguments
+///
+/// * `candidates` - The candidate strings to match.
+/// * `query` - The query to match against each candidate.
+pub fn rank_par(candidates: &[&str], query: &str) -> Vec<(usize, Result)> {
+    return rank_par_query(candidates, &Query::new(query));
+}
+
+/// Rank CANDIDATES against the compiled QUERY, like `rank_par`.
+pub fn rank_par_query(candidates: &[&str], query: &Query) -> Vec<(usize, Result)> {
+    let threads: usize = thread::available_parallelism().map_or(1, |count| count.get());
+    return rank_chunked(candidates, query, threads);
+}
+
+/// Rank CANDIDATES against QUERY, split into one chunk per thread for
+/// THREADS threads.
+fn rank_chunked(candidates: &[&str], query: &Query, threads: usize) -> Vec<(usize, Result)> {
+    let chunk_size: usize = candidates.len().div_ceil(threads).max(1);
+    let mut ranked: Vec<(usize, Result)> = thread::scope(|scope| {
+        let handles: Vec<_> = candidates
+            .chunks(chunk_size)
+            .enumerate()
+            .map(|(chunk, items)| {
+                scope.spawn(move || {
+                    let offset: usize = chunk * chunk_size;
+                    score_many_query(items, query)
+                        .into_iter()
+                        .enumerate()
+                        .filter_map(|(index, result)| result.map(|result| (offset + index, result)))
+                        .collect::<Vec<(usize, Result)>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    });
+    sort_ranked(&mut ranked, query.tiebreak, |index| candidates[index]);
+    return ranked;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use query::Tiebreak;
+    use rank::rank_items_query;
+
+    /// Reduce RANKED to the index, indices and score of each match.
+    fn summary(ranked: &[(usize, Result)]) -> Vec<(usize, &[i32], i64)> {
+        return ranked
+            .iter()
+            .map(|(index, result)| (*index, result.indices.as_slice(), result.score.0))
+            .collect();
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        // Repeated names tie on score and land in every chunk
+        let candidates: Vec<String> = (0..1000)
+            .map(|index| match index % 4 {
+                0 => format!("src/file{}.rs", index % 13),
+                1 => format!("test/file_{}", index % 7),
+                2 => "src/file.rs".to_string(),
+                _ => format!("docs/{}/readme", index),
+            })
+            .collect();
+        let candidates: Vec<&str> = candidates.iter().map(String::as_str).collect();
+        let items: Vec<(usize, &str)> = candidates.iter().cloned().enumerate().collect();
+        for tiebreak in [Tiebreak::Input, Tiebreak::Natural] {
+            let query: Query = Query::new("fl").tiebreak(tiebreak);
+            let sequential: Vec<(usize, Result)> = rank_items_query(&items, &query);
+            for threads in [1, 3, 4, 7] {
+                let parallel: Vec<(usize, Result)> = rank_chunked(&candidates, &query, threads);
+                assert_eq!(
+                    summary(&parallel),
+                    summary(&sequential),
+                    "{:?} on {} threads",
+                    tiebreak,
+                    threads
+                );
+            }
+            let parallel: Vec<(usize, Result)> = rank_par_query(&candidates, &query);
+            assert_eq!(summary(&parallel), summary(&sequential));
+        }
+    }
+}
@@ -0,0 +1,84 @@
+/**
+ * $File: path_symbol.rs $
+ * $Date: 2026-10-16 00:39:58 $
+ * $Revision: $
+ * $Creator: Jen-Chieh Shen $
+ * $Notice: See LICENSE.txt for modification and distribution information
+ *                   Copyright © 2026 by Shen, Jen-Chieh $
+ */
+use preset::Preset;
+use query::Query;
+use search::{score_query, Result};
+use value::Score;
+
+/// Result of scoring a `path:symbol` candidate part by part.
+#[derive(Debug, Clone)]
+pub struct PathSymbolResult {
+    /// Match of the path query against the path part, if one was given.
+    pub path: Option<Result>,
+    /// Match of the symbol query against the symbol part, if one was given.
+    pub symbol: Option<Result>,
+    /// Character index where the symbol part starts in the candidate; add
+    /// it to the symbol indices to highlight the whole candidate.
+    pub symbol_offset: usize,
+    /// Sum of the part scores.
+    pub score: Score,
+}
+
+/// Return the char and byte index of the `:` between path and symbol.
+///
+/// `::` belongs to the symbol and a leading `C:` to a Windows path, so
+/// neither splits.
+fn split_point(str: &str) -> Option<(usize, usize)> {
+    let chars: Vec<char> = str.chars().collect();
+    for (n, (byte, ch)) in str.char_indices().enumerate() {
+        if ch != ':' {
+            continue;
+        }
+        let doubled: bool = chars.get(n + 1) == Some(&':') || (0 < n && chars[n - 1] == ':');
+        let drive: bool = n == 1 && matches!(chars.get(2), Some('/') | Some('\\'));
+        if !doubled && !drive {
+            return Some((n, byte));
+        }
+    }
+    return None;
+}
+
+/// Score CANDIDATE of the form `path:symbol` against QUERY, matching the
+/// path under `Preset::Default` and the symbol under `Preset::CodeSymbol`.
+///
+/// QUERY splits the same way: `search:best` matches `search` against the
+/// path and `best` against the symbol, while a query without `:` only
+/// targets the symbol. Every part the query names must match.
+pub fn score_path_symbol(candidate: &str, query: &str) -> Option<PathSymbolResult> {
+    let (path, symbol, symbol_offset): (&str, &str, usize) = match split_point(candidate) {
+        Some((n, byte)) => (&candidate[..byte], &candidate[byte + 1..], n + 1),
+        None => ("", candidate, 0),
+    };
+    let (path_query, symbol_query): (&str, &str) = match split_point(query) {
+        Some((_, byte)) => (&query[..byte], &query[byte + 1..]),
+        None => ("", query),
+    };
+
+    let mut result: PathSymbolResult = PathSymbolResult {
+        path: None,
+        symbol: None,
+        symbol_offset,
+        score: Score(0),
+    };
+    if !path_query.is_empty() {
+        let part: Result = score_query(path, &Query::new(path_query))?;
+        result.score = result.score.saturating_add(part.score);
+        result.path = Some(part);
+    }
+    if !symbol_query.is_empty() {
+        let query: Query = Query::new(symbol_query).preset(Preset::CodeSymbol);
+        let part: Result = score_query(symbol, &query)?;
+        result.score = result.score.saturating_add(part.score);
+        result.symbol = Some(part);
+    }
+    if result.path.is_none() && result.symbol.is_none() {
+        return None;
+    }
+    return Some(result);
+}
@@ -0,0 +1,87 @@
+/**
+ * $File: preset.rs $
+ * $Date: 2026-10-16 00:38:19 $
+ * $Revision: $
+ * $Creator: Jen-Chieh Shen $
+ * $Notice: See LICENSE.txt for modification and distribution information
+ *                   Copyright © 2026 by Shen, Jen-Chieh $
+ */
+use search::consts::{INITIALISM_BONUS, PREFIX_TIER_BONUS};
+use search::{BasepathGroup, ScoreOptions};
+
+/// Ready-made `ScoreOptions` for common kinds of candidates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Preset {
+    /// The original flx weights.
+    #[default]
+    Default,
+    /// Code completion: exact prefixes rank in a tier of their own above
+    /// every fuzzy match, and CamelCase initialisms get a bonus.
+    Completion,
+    /// Qualified code symbols like `std::collections::HashMap`: `::`, `.`,
+    /// `->` and `#` split scopes, and the bare symbol name is the basepath.
+    CodeSymbol,
+    /// URLs from browser history or bookmarks: the scheme is ignored, `?`,
+    /// `&`, `=` and `#` split words, and both the host and the last path
+    /// segment are weighted like a basepath.
+    Url,
+    /// Contacts and identifiers like `user@domain` or `first.last`: `@`
+    /// splits groups, and the whole local part before it is the basepath,
+    /// with `.` only separating words.
+    Contact,
+    /// Human-readable command names like `Toggle Line Numbers`: words are
+    /// split by spaces, dashes and underscores only, initialisms get a
+    /// bonus, and no character is penalized as an extension.
+    CommandPalette,
+}
+
+impl Preset {
+    /// Return the options this preset stands for.
+    pub fn options(self) -> ScoreOptions {
+        let mut options: ScoreOptions = ScoreOptions::default();
+        match self {
+            Preset::Default => {}
+            Preset::Completion => {
+                options.prefix_bonus = PREFIX_TIER_BONUS;
+                options.initialism_bonus = INITIALISM_BONUS;
+            }
+            Preset::CodeSymbol => {
+                options.word_separators.extend(['>', '#']);
+                options.group_separators = ["::", ".", "->", "#"]
+                    .iter()
+                    .map(|sep| sep.to_string())
+                    .collect();
+                // A `.` separates members here, not an extension
+                options.extension_penalty = 0;
+            }
+            Preset::Url => {
+                options.url_scheme_group = true;
+                options.word_separators.extend(['?', '&', '=', '#']);
+                options.group_separators = vec!["/".to_string()];
+                options.basepath_group = BasepathGroup::FirstAndLast;
+                // Host names are full of dots that aren't extensions
+                options.extension_penalty = 0;
+            }
+            Preset::Contact => {
+                options.word_separators.push('@');
+                options.group_separators = vec!["@".to_string()];
+                options.basepath_group = BasepathGroup::First;
+                options.extension_penalty = 0;
+            }
+            Preset::CommandPalette => {
+                options.word_separators = vec![' ', '-', '_'];
+                options.initialism_bonus = INITIALISM_BONUS;
+                // Long names shouldn't lose out for having more words
+                options.basepath_word_penalty = 0;
+                options.extension_penalty = 0;
+            }
+        }
+        return options;
+    }
+}
+
+impl From<Preset> for ScoreOptions {
+    fn from(preset: Preset) -> ScoreOptions {
+        return preset.options();
+    }
+}
@@ -0,0 +1,197 @@
+/**
+ * $File: query.rs $
+ * $Date: 2026-10-16 00:33:35 $
+ * $Revision: $
+ * $Creator: Jen-Chieh Shen $
+ * $Notice: See LICENSE.txt for modification and distribution information
+ *                   Copyright © 2026 by Shen, Jen-Chieh $
+ */
+use preset::Preset;
+use search::{Prepared, Result, ScoreOptions, StrInfo};
+
+/// How query characters compare against candidate characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CaseMode {
+    /// The flx behaviour: lowercase query characters match either case,
+    /// uppercase ones only match uppercase.
+    #[default]
+    Mixed,
+    /// Case-insensitive, unless the query contains an uppercase character.
+    Smart,
+    /// Ignore case entirely.
+    Insensitive,
+    /// Characters only match the same case.
+    Sensitive,
+}
+
+impl CaseMode {
+    /// Settle `CaseMode::Smart` against QUERY, into `CaseMode::Insensitive`
+    /// or `CaseMode::Sensitive`. Other modes are returned as is.
+    pub fn resolve(self, query: &str) -> CaseMode {
+        if self != CaseMode::Smart {
+            return self;
+        }
+        if query.chars().any(char::is_uppercase) {
+            return CaseMode::Sensitive;
+        }
+        return CaseMode::Insensitive;
+    }
+}
+
+/// How rankings order candidates whose scores are equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Tiebreak {
+    /// Keep the input order.
+    #[default]
+    Input,
+    /// Lexicographic string order, character by character, so `file10`
+    /// comes before `file2`. Equal strings keep the input order.
+    Lexicographic,
+    /// Natural string order, where digit runs compare by value, so
+    /// `file2` comes before `file10`. Equal strings keep the input order.
+    Natural,
+}
+
+/// A reusable query, with every per-call option attached.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Query {
+    pub(crate) text: String,
+    pub(crate) options: ScoreOptions,
+    pub(crate) case_mode: CaseMode,
+    pub(crate) word_starts_only: bool,
+    pub(crate) max_gap: Option<u32>,
+    pub(crate) tiebreak: Tiebreak,
+}
+
+impl Query {
+    pub fn new(text: &str) -> Query {
+        Query {
+            text: text.to_string(),
+            options: ScoreOptions::default(),
+            case_mode: CaseMode::default(),
+            word_starts_only: false,
+            max_gap: None,
+            tiebreak: Tiebreak::default(),
+        }
+    }
+
+    /// Return the query text.
+    pub fn text(&self) -> &str {
+        return &self.text;
+    }
+
+    /// Score with OPTIONS instead of the default weights.
+    pub fn options(mut self, options: ScoreOptions) -> Query {
+        self.options = options;
+        return self;
+    }
+
+    /// Score with the options of PRESET.
+    pub fn preset(self, preset: Preset) -> Query {
+        return self.options(preset.options());
+    }
+
+    /// Compare characters according to MODE.
+    pub fn case_mode(mut self, mode: CaseMode) -> Query {
+        self.case_mode = mode;
+        return self;
+    }
+
+    /// Shorthand for `case_mode(CaseMode::Smart)`.
+    pub fn smart_case(self) -> Query {
+        return self.case_mode(CaseMode::Smart);
+    }
+
+    /// Shorthand for `case_mode(CaseMode::Insensitive)`.
+    pub fn ignore_case(self) -> Query {
+        return self.case_mode(CaseMode::Insensitive);
+    }
+
+    /// Shorthand for `case_mode(CaseMode::Sensitive)`.
+    pub fn case_sensitive(self) -> Query {
+        return self.case_mode(CaseMode::Sensitive);
+    }
+
+    /// Only let query characters match at the start of a word.
+    pub fn word_starts_only(mut self) -> Query {
+        self.word_starts_only = true;
+        return self;
+    }
+
+    /// Reject matches that skip more than GAP characters between two
+    /// consecutive query characters.
+    pub fn max_gap(mut self, gap: u32) -> Query {
+        self.max_gap = Some(gap);
+        return self;
+    }
+
+    /// Order equal scores by TIEBREAK when ranking.
+    pub fn tiebreak(mut self, tiebreak: Tiebreak) -> Query {
+        self.tiebreak = tiebreak;
+        return self;
+    }
+}
+
+/// A set of queries matched together against one candidate at a time.
+///
+/// The candidate's heatmap is built once per distinct `ScoreOptions` and
+/// its occurrence table once per case mode, instead of once per query.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct QuerySet {
+    queries: Vec<Query>,
+}
+
+impl QuerySet {
+    /// Create a set of QUERIES.
+    pub fn new(queries: Vec<Query>) -> QuerySet {
+        return QuerySet { queries };
+    }
+
+    /// The queries of the set, in the order they were given.
+    pub fn queries(&self) -> &[Query] {
+        return &self.queries;
+    }
+
+    /// Number of queries in the set.
+    pub fn len(&self) -> usize {
+        return self.queries.len();
+    }
+
+    /// Check if the set has no queries.
+    pub fn is_empty(&self) -> bool {
+        return self.queries.is_empty();
+    }
+
+    /// Match every query against CANDIDATE.
+    ///
+    /// Results come in query order, `None` where a query doesn't match, as
+    /// `score_query` would return them.
+    pub fn score(&self, candidate: &str) -> Vec<Option<Result>> {
+        let mut prepared: Vec<(&ScoreOptions, Prepared)> = Vec::new();
+        let mut tables: Vec<(usize, CaseMode, StrInfo)> = Vec::new();
+        let mut results: Vec<Option<Result>> = Vec::with_capacity(self.queries.len());
+        for query in &self.queries {
+            let index: usize = match prepared.iter().position(|p| *p.0 == query.options) {
+                Some(index) => index,
+                None => {
+                    prepared.push((&query.options, Prepared::new(candidate, &query.options)));
+                    prepared.len() - 1
+                }
+            };
+            let case_mode: CaseMode = query.case_mode.resolve(query.text());
+            let table: usize = match tables.iter().position(|t| t.0 == index && t.1 == case_mode) {
+                Some(table) => table,
+                None => {
+                    tables.push((index, case_mode, prepared[index].1.str_info(case_mode)));
+                    tables.len() - 1
+                }
+            };
+            let result: Option<Result> = prepared[index]
+                .1
+                .score(query, &tables[table].2)
+                .unwrap_or(None);
+            results.push(result);
+        }
+        return results;
+    }
+}
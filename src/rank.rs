@@ -0,0 +1,430 @@
+/**
+ * $File: rank.rs $
+ * $Date: 2026-10-16 00:31:19 $
+ * $Revision: $
+ * $Creator: Jen-Chieh Shen $
+ * $Notice: See LICENSE.txt for modification and distribution information
+ *                   Copyright © 2026 by Shen, Jen-Chieh $
+ */
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+
+use query::{Query, Tiebreak};
+use search::{is_path_separator, score_query, Result};
+use value::Score;
+
+/// A group key together with the ranked results filed under it.
+pub type RankedGroup<K, T> = (K, Vec<(T, Result)>);
+
+/// One page of a ranking, with the number of matches in the whole ranking.
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<(T, Result)>,
+    pub total: usize,
+}
+
+/// Rank ITEMS against QUERY, keeping each payload next to its result.
+///
+/// Items that don't match are dropped. The rest are sorted by descending
+/// score; equal scores keep their input order, so the list doesn't
+/// reshuffle between keystrokes when many candidates tie.
+///
+/// # Arguments
+///
+/// * `items` - Pairs of payload and the candidate string to match.
+/// * `query` - The query to match against each candidate.
+pub fn rank_items<T: Clone>(items: &[(T, &str)], query: &str) -> Vec<(T, Result)> {
+    return rank_items_query(items, &Query::new(query));
+}
+
+/// Rank ITEMS against the compiled QUERY, like `rank_items`.
+///
+/// Equal scores are ordered by the query's `Tiebreak`, and candidates
+/// that tie on it as well keep their input order. The order is total, so
+/// the same items and query always give the same list.
+pub fn rank_items_query<T: Clone>(items: &[(T, &str)], query: &Query) -> Vec<(T, Result)> {
+    let mut ranked: Vec<(usize, Result)> = Vec::new();
+    for (index, (_, candidate)) in items.iter().enumerate() {
+        if let Some(result) = score_query(candidate, query) {
+            ranked.push((index, result));
+        }
+    }
+    sort_ranked(&mut ranked, query.tiebreak, |index| items[index].1);
+    return with_payloads(items, ranked);
+}
+
+/// Return the N best matches of QUERY among ITEMS.
+///
+/// Only N results are held at a time, in a bounded heap, instead of
+/// collecting and sorting every match. The returned list equals the first
+/// N entries of `rank_items_query`, ties included: they are broken by the
+/// query's `Tiebreak` and then input order.
+///
+/// # Arguments
+///
+/// * `items` - Pairs of payload and the candidate string to match.
+/// * `query` - The query to match against each candidate.
+/// * `n` - Number of results to keep.
+pub fn rank_top_n<T: Clone>(items: &[(T, &str)], query: &Query, n: usize) -> Vec<(T, Result)> {
+    if n == 0 {
+        return Vec::new();
+    }
+    let (ranked, _) = top_matches(items, query, n);
+    return with_payloads(items, ranked);
+}
+
+/// Return the page of the ranking of QUERY among ITEMS that starts at
+/// OFFSET and holds at most LIMIT results.
+///
+/// The page equals that slice of `rank_items_query`, whose ties are broken
+/// by the query's `Tiebreak` and then input order, so consecutive pages
+/// neither repeat nor skip a match, and `total` counts every match. Only
+/// OFFSET + LIMIT results are held at a time, like `rank_top_n`, so
+/// virtual scrolling doesn't need the whole ranked list.
+///
+/// # Arguments
+///
+/// * `items` - Pairs of payload and the candidate string to match.
+/// * `query` - The query to match against each candidate.
+/// * `offset` - Number of leading results to skip.
+/// * `limit` - Maximum number of results on the page.
+pub fn rank_page<T: Clone>(
+    items: &[(T, &str)],
+    query: &Query,
+    offset: usize,
+    limit: usize,
+) -> Page<T> {
+    let (mut ranked, total) = top_matches(items, query, offset.saturating_add(limit));
+    let ranked: Vec<(usize, Result)> = ranked.split_off(offset.min(ranked.len()));
+    return Page {
+        items: with_payloads(items, ranked),
+        total,
+    };
+}
+
+/// Return the input indices and results of the N best matches of QUERY
+/// among ITEMS, in ranking order, and the number of matches.
+fn top_matches<T>(items: &[(T, &str)], query: &Query, n: usize) -> (Vec<(usize, Result)>, usize) {
+    let mut total: usize = 0;
+    // Min-heap on the worst kept match, so it is the one to evict
+    let mut heap: BinaryHeap<Reverse<HeapEntry>> = BinaryHeap::with_capacity(n.min(items.len()));
+    for (index, (_, candidate)) in items.iter().enumerate() {
+        if let Some(result) = score_query(candidate, query) {
+            total += 1;
+            let entry: HeapEntry = HeapEntry {
+                index,
+                candidate,
+                tiebreak: query.tiebreak,
+                result,
+            };
+            if heap.len() < n {
+                heap.push(Reverse(entry));
+            } else if heap.peek().is_some_and(|worst| worst.0 < entry) {
+                heap.pop();
+                heap.push(Reverse(entry));
+            }
+        }
+    }
+    let ranked: Vec<(usize, Result)> = heap
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Reverse(entry)| (entry.index, entry.result))
+        .collect();
+    return (ranked, total);
+}
+
+/// A match of the item at `index`, ordered so that better matches are
+/// greater, following `rank_order`.
+struct HeapEntry<'a> {
+    index: usize,
+    candidate: &'a str,
+    tiebreak: Tiebreak,
+    result: Result,
+}
+
+impl<'a> PartialEq for HeapEntry<'a> {
+    fn eq(&self, other: &HeapEntry<'a>) -> bool {
+        return self.cmp(other) == Ordering::Equal;
+    }
+}
+
+impl<'a> Eq for HeapEntry<'a> {}
+
+impl<'a> PartialOrd for HeapEntry<'a> {
+    fn partial_cmp(&self, other: &HeapEntry<'a>) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl<'a> Ord for HeapEntry<'a> {
+    fn cmp(&self, other: &HeapEntry<'a>) -> Ordering {
+        let this = (self.result.score, self.candidate, self.index);
+        let that = (other.result.score, other.candidate, other.index);
+        return rank_order(self.tiebreak, that, this);
+    }
+}
+
+/// Compare two matches, each given as score, candidate string and input
+/// index, in ranking order: higher scores first, then TIEBREAK, then
+/// earlier input.
+pub(crate) fn rank_order(
+    tiebreak: Tiebreak,
+    a: (Score, &str, usize),
+    b: (Score, &str, usize),
+) -> Ordering {
+    let order: Ordering = b.0.cmp(&a.0);
+    let order: Ordering = match tiebreak {
+        Tiebreak::Input => order,
+        Tiebreak::Lexicographic => order.then_with(|| a.1.cmp(b.1)),
+        Tiebreak::Natural => order.then_with(|| natural_cmp(a.1, b.1)),
+    };
+    return order.then(a.2.cmp(&b.2));
+}
+
+/// Sort RANKED, pairs of input index and result, by `rank_order`.
+/// CANDIDATE returns the candidate string of an input index.
+pub(crate) fn sort_ranked<'a, F: Fn(usize) -> &'a str>(
+    ranked: &mut [(usize, Result)],
+    tiebreak: Tiebreak,
+    candidate: F,
+) {
+    ranked.sort_by(|a, b| {
+        rank_order(
+            tiebreak,
+            (a.1.score, candidate(a.0), a.0),
+            (b.1.score, candidate(b.0), b.0),
+        )
+    });
+}
+
+/// Compare A and B in natural order: runs of ASCII digits compare by
+/// their value, everything else character by character.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a: &str = a;
+    let mut b: &str = b;
+    loop {
+        let (ach, bch) = match (a.chars().next(), b.chars().next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ach), Some(bch)) => (ach, bch),
+        };
+        if ach.is_ascii_digit() && bch.is_ascii_digit() {
+            let (adigits, arest) = split_digits(a);
+            let (bdigits, brest) = split_digits(b);
+            let avalue: &str = adigits.trim_start_matches('0');
+            let bvalue: &str = bdigits.trim_start_matches('0');
+            let order: Ordering = avalue
+                .len()
+                .cmp(&bvalue.len())
+                .then_with(|| avalue.cmp(bvalue));
+            if order != Ordering::Equal {
+                return order;
+            }
+            a = arest;
+            b = brest;
+        } else {
+            if ach != bch {
+                return ach.cmp(&bch);
+            }
+            a = &a[ach.len_utf8()..];
+            b = &b[bch.len_utf8()..];
+        }
+    }
+}
+
+/// Split TEXT after its leading run of ASCII digits.
+fn split_digits(text: &str) -> (&str, &str) {
+    let end: usize = text
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(text.len());
+    return text.split_at(end);
+}
+
+/// Swap the input indices of RANKED for the payloads of ITEMS.
+fn with_payloads<T: Clone>(items: &[(T, &str)], ranked: Vec<(usize, Result)>) -> Vec<(T, Result)> {
+    return ranked
+        .into_iter()
+        .map(|(index, result)| (items[index].0.clone(), result))
+        .collect();
+}
+
+/// Rank ITEMS against QUERY, scoring the PRIORITY items first.
+///
+/// PRIORITY lists indices into ITEMS, like the rows currently on screen.
+/// Those are scored and ranked first and handed to ON-PARTIAL, so a UI can
+/// show them before the long tail is done. The returned ranking covers
+/// every item and equals that of `rank_items_query`.
+///
+/// # Arguments
+///
+/// * `items` - Pairs of payload and the candidate string to match.
+/// * `query` - The query to match against each candidate.
+/// * `priority` - Indices of the items to score first; out-of-range ones
+///   are ignored.
+/// * `on_partial` - Called once with the ranked priority matches.
+pub fn rank_items_prioritized<T: Clone, F: FnOnce(&[(T, Result)])>(
+    items: &[(T, &str)],
+    query: &Query,
+    priority: &[usize],
+    on_partial: F,
+) -> Vec<(T, Result)> {
+    let mut scored: Vec<Option<Option<Result>>> = vec![None; items.len()];
+    let mut partial: Vec<(usize, Result)> = Vec::new();
+    for &index in priority {
+        if let Some(slot) = scored.get_mut(index) {
+            if slot.is_none() {
+                let result: Option<Result> = score_query(items[index].1, query);
+                if let Some(result) = &result {
+                    partial.push((index, result.clone()));
+                }
+                *slot = Some(result);
+            }
+        }
+    }
+    // Rank in the same order as the full ranking
+    sort_ranked(&mut partial, query.tiebreak, |index| items[index].1);
+    on_partial(&with_payloads(items, partial));
+
+    let mut ranked: Vec<(usize, Result)> = Vec::new();
+    for (index, ((_, candidate), slot)) in items.iter().zip(scored).enumerate() {
+        let result: Option<Result> = match slot {
+            Some(result) => result,
+            None => score_query(candidate, query),
+        };
+        if let Some(result) = result {
+            ranked.push((index, result));
+        }
+    }
+    sort_ranked(&mut ranked, query.tiebreak, |index| items[index].1);
+    return with_payloads(items, ranked);
+}
+
+/// Drop repeated candidate strings from ITEMS, keeping the first payload.
+///
+/// Lists assembled from several sources often repeat candidates; removing
+/// them before ranking saves scoring time and keeps results clean. Input
+/// order is preserved.
+pub fn dedup_items<'a, T: Clone>(items: &[(T, &'a str)]) -> Vec<(T, &'a str)> {
+    return merge_items(items)
+        .into_iter()
+        .map(|(mut payloads, candidate)| (payloads.swap_remove(0), candidate))
+        .collect();
+}
+
+/// Collapse repeated candidate strings in ITEMS, merging their payloads.
+///
+/// Each candidate appears once, at the position of its first occurrence,
+/// with the payloads of every occurrence in input order.
+pub fn merge_items<'a, T: Clone>(items: &[(T, &'a str)]) -> Vec<(Vec<T>, &'a str)> {
+    let mut merged: Vec<(Vec<T>, &'a str)> = Vec::new();
+    let mut positions: HashMap<&'a str, usize> = HashMap::new();
+    for (payload, candidate) in items {
+        match positions.get(candidate) {
+            Some(&index) => merged[index].0.push(payload.clone()),
+            None => {
+                positions.insert(candidate, merged.len());
+                merged.push((vec![payload.clone()], candidate));
+            }
+        }
+    }
+    return merged;
+}
+
+/// Rank ITEMS against QUERY and group the results by their tag.
+///
+/// Each item carries an optional tag naming its source (project, buffer,
+/// recent, ...). Groups come out in the order their tag first appears in
+/// ITEMS, so multi-source pickers keep their source order; within a group
+/// results are ranked like `rank_items_query` and cut to LIMIT entries.
+///
+/// # Arguments
+///
+/// * `items` - Triples of payload, candidate string and optional tag.
+/// * `query` - The query to match against each candidate.
+/// * `limit` - Maximum number of results kept per group.
+pub fn rank_items_by_tag<T: Clone, K: Clone + PartialEq>(
+    items: &[(T, &str, Option<K>)],
+    query: &Query,
+    limit: usize,
+) -> Vec<RankedGroup<Option<K>, T>> {
+    let mut groups: Vec<RankedGroup<Option<K>, usize>> = Vec::new();
+    for (_, _, tag) in items {
+        if !groups.iter().any(|group| group.0 == *tag) {
+            groups.push((tag.clone(), Vec::new()));
+        }
+    }
+    for (index, (_, candidate, tag)) in items.iter().enumerate() {
+        if let Some(result) = score_query(candidate, query) {
+            if let Some(group) = groups.iter_mut().find(|group| group.0 == *tag) {
+                group.1.push((index, result));
+            }
+        }
+    }
+    groups.retain(|group| !group.1.is_empty());
+    return groups
+        .into_iter()
+        .map(|(tag, mut ranked)| {
+            sort_ranked(&mut ranked, query.tiebreak, |index| items[index].1);
+            ranked.truncate(limit);
+            let ranked: Vec<(T, Result)> = ranked
+                .into_iter()
+                .map(|(index, result)| (items[index].0.clone(), result))
+                .collect();
+            (tag, ranked)
+        })
+        .collect();
+}
+
+/// Rank ITEMS against QUERY and group the results by parent directory.
+///
+/// The parent directory is everything before the last path separator, or
+/// the empty string for top-level candidates. Groups are ordered by their
+/// best match; within a group results are ranked like `rank_items_query`
+/// and cut to LIMIT entries.
+///
+/// # Arguments
+///
+/// * `items` - Pairs of payload and the candidate path to match.
+/// * `query` - The query to match against each candidate.
+/// * `limit` - Maximum number of results kept per directory.
+pub fn rank_items_by_directory<T: Clone>(
+    items: &[(T, &str)],
+    query: &Query,
+    limit: usize,
+) -> Vec<RankedGroup<String, T>> {
+    let mut ranked: Vec<(usize, Result)> = Vec::new();
+    for (index, (_, candidate)) in items.iter().enumerate() {
+        if let Some(result) = score_query(candidate, query) {
+            ranked.push((index, result));
+        }
+    }
+    sort_ranked(&mut ranked, query.tiebreak, |index| items[index].1);
+
+    let mut groups: Vec<RankedGroup<String, T>> = Vec::new();
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    for (index, result) in ranked {
+        let (payload, candidate) = &items[index];
+        let payload: T = payload.clone();
+        let directory: &str = parent_directory(candidate);
+        match positions.get(directory) {
+            Some(&index) => groups[index].1.push((payload, result)),
+            None => {
+                positions.insert(directory, groups.len());
+                groups.push((directory.to_string(), vec![(payload, result)]));
+            }
+        }
+    }
+    for group in &mut groups {
+        group.1.truncate(limit);
+    }
+    return groups;
+}
+
+/// Return the part of CANDIDATE before its last path separator.
+fn parent_directory(candidate: &str) -> &str {
+    match candidate.rfind(|ch: char| is_path_separator(&ch)) {
+        Some(index) => &candidate[..index],
+        None => "",
+    }
+}
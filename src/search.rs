@@ -6,34 +6,355 @@
  * $Notice: See LICENSE.txt for modification and distribution information
  *                   Copyright © 2021 by Shen, Jen-Chieh $
  */
-use std::cmp::min;
+use std::borrow::Cow;
+use std::cmp::{max, min};
 use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
 
-/// List of characters that act as word separators in flx.
-pub const WORD_SEPARATORS: [u32; 7] = [
-    ' ' as u32,
-    '-' as u32,
-    '_' as u32,
-    ':' as u32,
-    '.' as u32,
-    '/' as u32,
-    '\\' as u32,
-];
+use error::FlxError;
+use explain::Component;
+use query::{CaseMode, Query};
+use value::Score;
 
-/// Magic number for default +/- score.
-const DEFAULT_SCORE: i32 = -35;
+use self::consts::*;
+
+/// Named bonuses and penalties of the flx algorithm.
+///
+/// Penalties are negative, so every constant is added to a score.
+pub mod consts {
+    /// List of characters that act as word separators in flx.
+    pub const WORD_SEPARATORS: [u32; 7] = [
+        ' ' as u32,
+        '-' as u32,
+        '_' as u32,
+        ':' as u32,
+        '.' as u32,
+        '/' as u32,
+        '\\' as u32,
+    ];
+
+    /// Character that starts an extension.
+    pub const PENALTY_LEAD: char = '.';
+
+    /// Magic number for default +/- score.
+    pub const DEFAULT_SCORE: i32 = -35;
+
+    /// Bonus for the last character of the string.
+    pub const FINAL_CHAR_BONUS: i32 = 1;
+
+    /// Penalty for the character right after the penalty lead.
+    pub const EXTENSION_PENALTY: i32 = -45;
+
+    /// Penalty per group, applied when the string has separators.
+    pub const GROUP_COUNT_PENALTY: i32 = -2;
+
+    /// Bonus for every character of the basepath group.
+    pub const BASEPATH_BONUS: i32 = 35;
+
+    /// Penalty for the first group when it is not the basepath.
+    pub const FIRST_GROUP_PENALTY: i32 = -3;
+
+    /// Base penalty for the other groups that are not the basepath.
+    pub const NON_BASEPATH_PENALTY: i32 = -5;
+
+    /// Bonus for the first character of a word.
+    pub const WORD_START_BONUS: i32 = 85;
+
+    /// Penalty per word that comes before the last word of a group.
+    pub const WORD_ORDER_PENALTY: i32 = -3;
+
+    /// Penalty per character from the start of a word.
+    pub const CHAR_ORDER_PENALTY: i32 = -1;
+
+    /// Bonus for a matched character directly followed by another one.
+    pub const CONTIGUOUS_BASE: i32 = 60;
+
+    /// Extra bonus per character already in the contiguous run.
+    pub const CONTIGUOUS_BONUS: i32 = 15;
+
+    /// Number of run characters that earn `CONTIGUOUS_BONUS`.
+    pub const CONTIGUOUS_BONUS_CAP: i32 = 3;
+
+    /// Boost for a short query matching every character of the string.
+    pub const FULL_MATCH_BOOST: i32 = 10000;
+
+    /// Query lengths that earn `FULL_MATCH_BOOST`.
+    pub const FULL_MATCH_QUERY_LENGTH: [i32; 2] = [2, 4];
+
+    /// Bonus lifting exact and prefix matches above every fuzzy match,
+    /// used by `Preset::Completion`.
+    pub const PREFIX_TIER_BONUS: i32 = 100000;
+
+    /// Bonus for matching only word starts, used by `Preset::Completion`.
+    pub const INITIALISM_BONUS: i32 = 50;
+}
+
+/// Tunable constants used while scoring.
+///
+/// `ScoreOptions::default()` reproduces the original flx weights.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoreOptions {
+    /// Baseline score of every character.
+    pub default_score: i32,
+    /// Bonus for the first character of a word.
+    pub word_start_bonus: i32,
+    /// Bonus for a matched character directly followed by another one.
+    pub contiguous_base: i32,
+    /// Bonus added to every character of the basepath group.
+    pub basepath_bonus: i32,
+    /// Upper bound of the basepath boost earned per extra separator, or
+    /// `None` to leave it unbounded.
+    pub basepath_separator_boost_cap: Option<i32>,
+    /// Penalty applied to the basepath for each word it contains.
+    pub basepath_word_penalty: i32,
+    /// Leave the basepath extension out of matching.
+    ///
+    /// The extension still shapes the heatmap, and indices keep pointing
+    /// into the whole string.
+    pub ignore_extension: bool,
+    /// Match the query within the filename first, and only fall back to
+    /// the whole path when that fails.
+    pub filename_first: bool,
+    /// Penalty for strings with a hidden (dot-prefixed) path component.
+    ///
+    /// It is waived when the match includes the leading dot.
+    pub hidden_penalty: i32,
+    /// Penalty for each path separator in the string, on top of the
+    /// group-count penalty of the heatmap.
+    pub depth_penalty: i32,
+    /// Bonus for `MatchKind::Exact` and `MatchKind::Prefix` matches.
+    pub prefix_bonus: i32,
+    /// Bonus when every matched character starts a word, like `hm` in
+    /// `HashMap`.
+    pub initialism_bonus: i32,
+    /// Bonus for matching the first characters of the path components,
+    /// like `sru` in `src/render/ui.rs`. When set, such a match is tried
+    /// on its own and wins if it then outscores the regular match.
+    pub component_initials_bonus: i32,
+    /// Score added to the character after a penalty lead `.`.
+    pub extension_penalty: i32,
+    /// Characters that end a word.
+    pub word_separators: Vec<char>,
+    /// Strings that split the string into groups, like `/` for paths.
+    pub group_separators: Vec<String>,
+    /// Which groups with a word are weighted as the basepath.
+    pub basepath_group: BasepathGroup,
+    /// Bonus per extension, keyed without the leading dot, e.g. `rs`.
+    pub extension_boosts: HashMap<String, i32>,
+    /// Normalize the string with `normalize_path` before scoring.
+    ///
+    /// Indices then point into the normalized string.
+    pub normalize_path: bool,
+    /// Also resolve `..` components lexically when normalizing.
+    pub resolve_parent_dirs: bool,
+    /// Treat a leading `~/` as a group of its own with no words, so the
+    /// rest of the string scores as if the prefix were absent.
+    pub home_prefix_group: bool,
+    /// Treat a leading URL scheme like `https://` the same way.
+    pub url_scheme_group: bool,
+    /// Leave invisible characters, like soft hyphens and zero-width
+    /// joiners, out of matching. Indices still point into the string.
+    pub skip_invisible: bool,
+}
+
+/// Groups that `ScoreOptions` weights as the basepath.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BasepathGroup {
+    /// The last group with a word, like the filename of a path.
+    #[default]
+    Last,
+    /// The first group with a word, like the local part of an email.
+    First,
+    /// Both, like the host and last segment of a URL. The last one is
+    /// still reported as the basepath.
+    FirstAndLast,
+}
+
+impl Default for ScoreOptions {
+    fn default() -> ScoreOptions {
+        ScoreOptions {
+            default_score: DEFAULT_SCORE,
+            word_start_bonus: WORD_START_BONUS,
+            contiguous_base: CONTIGUOUS_BASE,
+            basepath_bonus: BASEPATH_BONUS,
+            basepath_separator_boost_cap: None,
+            basepath_word_penalty: 1,
+            ignore_extension: false,
+            filename_first: false,
+            hidden_penalty: 0,
+            depth_penalty: 0,
+            prefix_bonus: 0,
+            initialism_bonus: 0,
+            component_initials_bonus: 0,
+            extension_penalty: EXTENSION_PENALTY,
+            word_separators: WORD_SEPARATORS
+                .iter()
+                .filter_map(|&ch| char::from_u32(ch))
+                .collect(),
+            group_separators: Vec::new(),
+            basepath_group: BasepathGroup::Last,
+            extension_boosts: HashMap::new(),
+            normalize_path: false,
+            resolve_parent_dirs: false,
+            home_prefix_group: false,
+            url_scheme_group: false,
+            skip_invisible: false,
+        }
+    }
+}
+
+/// Builder methods for the weights; the other options are set through
+/// the fields.
+impl ScoreOptions {
+    /// Set the baseline score of every character to SCORE.
+    pub fn with_default_score(mut self, score: i32) -> ScoreOptions {
+        self.default_score = score;
+        return self;
+    }
+
+    /// Set the bonus for the first character of a word to BONUS.
+    pub fn with_word_start_bonus(mut self, bonus: i32) -> ScoreOptions {
+        self.word_start_bonus = bonus;
+        return self;
+    }
+
+    /// Set the bonus for adjacent matched characters to BONUS.
+    pub fn with_contiguous_base(mut self, bonus: i32) -> ScoreOptions {
+        self.contiguous_base = bonus;
+        return self;
+    }
+
+    /// Set the bonus for the basepath characters to BONUS.
+    pub fn with_basepath_bonus(mut self, bonus: i32) -> ScoreOptions {
+        self.basepath_bonus = bonus;
+        return self;
+    }
+
+    /// Set the penalty per basepath word to PENALTY.
+    pub fn with_basepath_word_penalty(mut self, penalty: i32) -> ScoreOptions {
+        self.basepath_word_penalty = penalty;
+        return self;
+    }
+
+    /// Set the score of the character after a `.` to PENALTY.
+    pub fn with_extension_penalty(mut self, penalty: i32) -> ScoreOptions {
+        self.extension_penalty = penalty;
+        return self;
+    }
+
+    /// Set the penalty for hidden path components to PENALTY.
+    pub fn with_hidden_penalty(mut self, penalty: i32) -> ScoreOptions {
+        self.hidden_penalty = penalty;
+        return self;
+    }
+
+    /// Set the penalty per path separator to PENALTY.
+    pub fn with_depth_penalty(mut self, penalty: i32) -> ScoreOptions {
+        self.depth_penalty = penalty;
+        return self;
+    }
+
+    /// Set the bonus for exact and prefix matches to BONUS.
+    pub fn with_prefix_bonus(mut self, bonus: i32) -> ScoreOptions {
+        self.prefix_bonus = bonus;
+        return self;
+    }
+
+    /// Set the bonus for word-start-only matches to BONUS.
+    pub fn with_initialism_bonus(mut self, bonus: i32) -> ScoreOptions {
+        self.initialism_bonus = bonus;
+        return self;
+    }
+
+    /// Set the bonus for path component initials to BONUS.
+    pub fn with_component_initials_bonus(mut self, bonus: i32) -> ScoreOptions {
+        self.component_initials_bonus = bonus;
+        return self;
+    }
+
+    /// Cap the basepath boost per extra separator at CAP.
+    pub fn with_basepath_separator_boost_cap(mut self, cap: i32) -> ScoreOptions {
+        self.basepath_separator_boost_cap = Some(cap);
+        return self;
+    }
+
+    /// Add BONUS for strings with EXTENSION, given without the dot.
+    pub fn with_extension_boost(mut self, extension: &str, bonus: i32) -> ScoreOptions {
+        self.extension_boosts.insert(extension.to_string(), bonus);
+        return self;
+    }
+}
+
+impl Hash for ScoreOptions {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let ScoreOptions {
+            default_score,
+            word_start_bonus,
+            contiguous_base,
+            basepath_bonus,
+            basepath_separator_boost_cap,
+            basepath_word_penalty,
+            ignore_extension,
+            filename_first,
+            hidden_penalty,
+            depth_penalty,
+            prefix_bonus,
+            initialism_bonus,
+            component_initials_bonus,
+            extension_penalty,
+            word_separators,
+            group_separators,
+            basepath_group,
+            extension_boosts,
+            normalize_path,
+            resolve_parent_dirs,
+            home_prefix_group,
+            url_scheme_group,
+            skip_invisible,
+        } = self;
+        default_score.hash(state);
+        word_start_bonus.hash(state);
+        contiguous_base.hash(state);
+        basepath_bonus.hash(state);
+        basepath_separator_boost_cap.hash(state);
+        basepath_word_penalty.hash(state);
+        ignore_extension.hash(state);
+        filename_first.hash(state);
+        hidden_penalty.hash(state);
+        depth_penalty.hash(state);
+        prefix_bonus.hash(state);
+        initialism_bonus.hash(state);
+        component_initials_bonus.hash(state);
+        extension_penalty.hash(state);
+        word_separators.hash(state);
+        group_separators.hash(state);
+        basepath_group.hash(state);
+        // Map order is unspecified, so hash the boosts sorted
+        let mut boosts: Vec<(&String, &i32)> = extension_boosts.iter().collect();
+        boosts.sort_unstable();
+        boosts.hash(state);
+        normalize_path.hash(state);
+        resolve_parent_dirs.hash(state);
+        home_prefix_group.hash(state);
+        url_scheme_group.hash(state);
+        skip_invisible.hash(state);
+    }
+}
 
 /// Check if char is a word character.
 ///
 ///  # Arguments
 ///
 /// * `char` - Character we use to check for word.
-fn word(char: Option<u32>) -> bool {
+/// * `separators` - Characters that act as word separators.
+fn word(char: Option<u32>, separators: &[u32]) -> bool {
     if char.is_none() {
         return false;
     }
     let ch: u32 = char.unwrap();
-    return !WORD_SEPARATORS.contains(&ch);
+    return !separators.contains(&ch);
 }
 
 /// The `flx` compatible uppercase checker.
@@ -54,96 +375,312 @@ fn is_uppercase(char: &Option<char>) -> bool {
 ///  # Arguments
 ///
 /// * `char` - Character we use to check for capitalization.
-fn capital(char: Option<u32>) -> bool {
+/// * `separators` - Characters that act as word separators.
+fn capital(char: Option<u32>, separators: &[u32]) -> bool {
     if char.is_none() {
         return false;
     }
     let ch: Option<char> = char::from_u32(char.unwrap());
-    return word(char) && is_uppercase(&ch);
+    return word(char, separators) && is_uppercase(&ch);
 }
 
 /// Check if LAST-CHAR is the end of a word and CHAR the start of the next.
 ///
 /// This function is camel-case aware.
-fn boundary(last_char: Option<u32>, char: Option<u32>) -> bool {
+fn boundary(last_char: Option<u32>, char: Option<u32>, separators: &[u32]) -> bool {
     if last_char.is_none() {
         return true;
     }
-    if !capital(last_char) && capital(char) {
+    if !capital(last_char, separators) && capital(char, separators) {
         return true;
     }
-    if !word(last_char) && word(char) {
+    if !word(last_char, separators) && word(char, separators) {
         return true;
     }
     return false;
 }
 
 /// Increment each element in VEC between BEG and END by INC.
-fn inc_vec(vec: &mut Vec<i32>, inc: Option<i32>, beg: Option<i32>, end: Option<i32>) {
+fn inc_vec<C: Cell>(
+    vec: &mut [C],
+    component: Component,
+    inc: Option<i32>,
+    beg: Option<i32>,
+    end: Option<i32>,
+) {
     let _inc = inc.unwrap_or(1);
     let mut _beg = beg.unwrap_or(0);
     let _end = end.unwrap_or(vec.len() as i32);
     while _beg < _end {
-        vec[_beg as usize] += _inc;
+        vec[_beg as usize].add(component, _inc);
         _beg += 1;
     }
 }
 
-/// Return hash-table for string where keys are characters.
-/// Value is a sorted list of indexes for character occurrences.
-fn get_hash_for_string(result: &mut HashMap<Option<u32>, VecDeque<Option<u32>>>, str: &str) {
-    result.clear();
-    let str_len: i32 = str.chars().count() as i32;
-    let mut index: i32 = str_len - 1;
-    let mut char: Option<u32>;
-    let mut down_char: Option<u32>;
+/// Occurrence table of a string: for each character, the sorted indexes
+/// where it appears.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StrInfo {
+    table: HashMap<Option<u32>, VecDeque<Option<u32>>>,
+}
+
+impl StrInfo {
+    /// Return hash-table for string where keys are characters.
+    /// Value is a sorted list of indexes for character occurrences.
+    ///
+    /// Unless CASE-MODE is `CaseMode::Sensitive`, capitals are also filed
+    /// under their lowercase key. `CaseMode::Insensitive` files them under
+    /// the lowercase key only, so the query must be lowercased as well.
+    /// Resolve `CaseMode::Smart` against the query first with
+    /// `CaseMode::resolve`.
+    pub fn build(str: &str, case_mode: CaseMode) -> StrInfo {
+        let chars: Vec<char> = str.chars().collect();
+        return StrInfo::build_in(&chars, case_mode, HashMap::new());
+    }
+
+    /// Like `build`, for the characters CHARS, filing the indexes into
+    /// TABLE in place of its contents.
+    ///
+    /// The lists already in TABLE keep their buffers for the characters
+    /// that occur again.
+    fn build_in(
+        chars: &[char],
+        case_mode: CaseMode,
+        mut table: HashMap<Option<u32>, VecDeque<Option<u32>>>,
+    ) -> StrInfo {
+        let fold: bool = case_mode != CaseMode::Sensitive;
+        let keep_case: bool = case_mode != CaseMode::Insensitive;
+        for indexes in table.values_mut() {
+            indexes.clear();
+        }
+        let mut result: HashMap<Option<u32>, VecDeque<Option<u32>>> = table;
+        let mut index: i32 = chars.len() as i32 - 1;
+        let mut char: Option<u32>;
+        let mut down_char: Option<u32>;
+
+        while 0 <= index {
+            char = Some(chars[index as usize] as u32);
 
-    while 0 <= index {
-        char = Some(str.chars().nth(index as usize).unwrap() as u32);
+            if fold && capital(char, &WORD_SEPARATORS) {
+                let valid: Option<char> = char::from_u32(char.unwrap());
+                down_char = Some(valid.unwrap().to_lowercase().next().unwrap() as u32);
+            } else {
+                down_char = char;
+            }
+
+            // Characters without case fold into themselves; file them once
+            if keep_case && down_char != char {
+                result
+                    .entry(char)
+                    .or_default()
+                    .push_front(Some(index as u32));
+            }
 
-        if capital(char) {
             result
-                .entry(char)
-                .or_insert_with(VecDeque::new)
+                .entry(down_char)
+                .or_default()
                 .push_front(Some(index as u32));
 
-            let valid: Option<char> = char::from_u32(char.unwrap());
-            down_char = Some(valid.unwrap().to_lowercase().next().unwrap() as u32);
-        } else {
-            down_char = char;
+            index -= 1;
+        }
+        result.retain(|_, indexes| !indexes.is_empty());
+        return StrInfo { table: result };
+    }
+
+    /// Iterate over the indexes a query character CH can match, in
+    /// ascending order.
+    pub fn occurrences(&self, ch: char) -> impl Iterator<Item = usize> + '_ {
+        return self
+            .table
+            .get(&Some(ch as u32))
+            .into_iter()
+            .flat_map(|indexes| indexes.iter().map(|index| index.unwrap() as usize));
+    }
+
+    /// Hand the table back to SCRATCH, for the next `Prepared::str_info_in`.
+    pub(crate) fn recycle(self, scratch: &mut Scratch) {
+        scratch.table = self.table;
+    }
+
+    /// Keep only the occurrences whose index satisfies KEEP.
+    pub(crate) fn retain<F: Fn(usize) -> bool>(&mut self, keep: F) {
+        for indexes in self.table.values_mut() {
+            indexes.retain(|index| keep(index.unwrap() as usize));
         }
+    }
+}
+
+/// Word and group layout of a string, as seen by the heatmap.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Segmentation {
+    /// Character ranges of each group, in string order.
+    ///
+    /// A group owns the separator that ends it.
+    pub groups: Vec<Range<usize>>,
+    /// Character indices where a word starts, in ascending order.
+    pub word_starts: Vec<usize>,
+    /// Character range of the basepath group, if any group has words.
+    pub basepath: Option<Range<usize>>,
+}
+
+/// Per-character scores of a string, with the segmentation they were
+/// computed from.
+///
+/// `Display` prints the scores in aligned, space-separated columns.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Heatmap {
+    pub(crate) scores: Vec<i32>,
+    pub(crate) segmentation: Segmentation,
+}
+
+impl Heatmap {
+    /// Return the score of the character at INDEX.
+    pub fn get(&self, index: usize) -> Option<i32> {
+        return self.scores.get(index).copied();
+    }
+
+    /// Return the number of characters in the heatmap.
+    pub fn len(&self) -> usize {
+        return self.scores.len();
+    }
+
+    /// Check if the heatmap has no characters.
+    pub fn is_empty(&self) -> bool {
+        return self.scores.is_empty();
+    }
+
+    /// Iterate over the character scores in string order.
+    pub fn iter(&self) -> std::slice::Iter<'_, i32> {
+        return self.scores.iter();
+    }
+
+    /// Return the character scores as a slice, in string order.
+    pub fn as_slice(&self) -> &[i32] {
+        return &self.scores;
+    }
+
+    /// Return the word and group layout behind the scores.
+    pub fn segmentation(&self) -> &Segmentation {
+        return &self.segmentation;
+    }
+
+    /// Take the character scores, dropping the segmentation.
+    pub fn into_vec(self) -> Vec<i32> {
+        return self.scores;
+    }
+}
 
-        result
-            .entry(down_char)
-            .or_insert_with(VecDeque::new)
-            .push_front(Some(index as u32));
+impl<'a> IntoIterator for &'a Heatmap {
+    type Item = &'a i32;
+    type IntoIter = std::slice::Iter<'a, i32>;
 
-        index -= 1;
+    fn into_iter(self) -> Self::IntoIter {
+        return self.scores.iter();
+    }
+}
+
+impl fmt::Display for Heatmap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, score) in self.scores.iter().enumerate() {
+            if 0 < index {
+                write!(f, " ")?;
+            }
+            write!(f, "{:>4}", score)?;
+        }
+        return Ok(());
     }
 }
 
 /// Generate the heatmap vector of string.
 ///
 /// See documentation for logic.
-pub fn get_heatmap_str(scores: &mut Vec<i32>, str: &str, group_separator: Option<char>) {
-    let str_len: usize = str.chars().count();
-    let str_last_index: usize = str_len - 1;
+pub fn get_heatmap_str(str: &str, group_separator: Option<char>) -> Heatmap {
+    let mut options: ScoreOptions = ScoreOptions::default();
+    options
+        .group_separators
+        .extend(group_separator.map(String::from));
+    return get_heatmap_str_with_options(str, &options);
+}
+
+/// Generate the heatmap vector of string, weighted and split by OPTIONS.
+fn get_heatmap_str_with_options(str: &str, options: &ScoreOptions) -> Heatmap {
+    let (scores, segmentation) = build_heatmap(str, options);
+    return Heatmap {
+        scores,
+        segmentation,
+    };
+}
+
+/// A heatmap entry: the plain score, or the score split by component.
+pub(crate) trait Cell: Copy {
+    /// Create an entry holding AMOUNT of COMPONENT.
+    fn new(component: Component, amount: i32) -> Self;
+
+    /// Add AMOUNT of COMPONENT to the entry.
+    fn add(&mut self, component: Component, amount: i32);
+}
+
+impl Cell for i32 {
+    fn new(_: Component, amount: i32) -> i32 {
+        return amount;
+    }
+
+    fn add(&mut self, _: Component, amount: i32) {
+        *self = self.saturating_add(amount);
+    }
+}
+
+/// Build the heatmap entries and segmentation of STR under OPTIONS.
+pub(crate) fn build_heatmap<C: Cell>(str: &str, options: &ScoreOptions) -> (Vec<C>, Segmentation) {
+    let chars: Vec<char> = str.chars().collect();
+    return build_heatmap_in(&chars, options, Vec::new());
+}
+
+/// Like `build_heatmap`, for the characters CHARS, filling the entries
+/// into SCORES in place of its contents.
+fn build_heatmap_in<C: Cell>(
+    chars: &[char],
+    options: &ScoreOptions,
+    mut scores: Vec<C>,
+) -> (Vec<C>, Segmentation) {
+    let str_len: usize = chars.len();
     scores.clear();
-    for _n in 0..str_len {
-        scores.push(DEFAULT_SCORE);
+    if str_len == 0 {
+        return (scores, Segmentation::default());
+    }
+    let mut prefix_len: usize = 0;
+    if options.home_prefix_group && chars[0] == '~' && chars.get(1).is_some_and(is_path_separator) {
+        prefix_len = 2;
+    } else if options.url_scheme_group {
+        prefix_len = url_scheme_len(chars).unwrap_or(0);
     }
-    let penalty_lead: u32 = '.' as u32;
+    if 0 < prefix_len && prefix_len < str_len {
+        let (scores, segmentation) = build_heatmap_in(&chars[prefix_len..], options, scores);
+        return prefixed(scores, segmentation, prefix_len, options.default_score);
+    }
+    let separators: Vec<u32> = options
+        .word_separators
+        .iter()
+        .map(|&ch| ch as u32)
+        .collect();
+    let group_separators: Vec<Vec<char>> = options
+        .group_separators
+        .iter()
+        .map(|sep| sep.chars().collect())
+        .filter(|sep: &Vec<char>| !sep.is_empty())
+        .collect();
+    let str_last_index: usize = str_len - 1;
+    scores.resize(str_len, C::new(Component::Base, options.default_score));
+    let penalty_lead: u32 = PENALTY_LEAD as u32;
     let mut group_alist: Vec<Vec<i32>> = vec![vec![-1, 0]];
 
     // final char bonus
-    scores[str_last_index] += 1;
+    scores[str_last_index].add(Component::FinalChar, FINAL_CHAR_BONUS);
 
     // Establish baseline mapping
     let mut last_char: Option<u32> = None;
     let mut group_word_count: i32 = 0;
-    let mut index1: usize = 0;
-
-    for char in str.chars() {
+    for (index1, &char) in chars.iter().enumerate() {
         // before we find any words, all separaters are
         // considered words of length 1.  This is so "foo/__ab"
         // gets penalized compared to "foo/ab".
@@ -153,20 +690,24 @@ pub fn get_heatmap_str(scores: &mut Vec<i32>, str: &str, group_separator: Option
             last_char
         };
 
-        if boundary(effective_last_char, Some(char as u32)) {
+        if boundary(effective_last_char, Some(char as u32), &separators) {
             group_alist[0].insert(2, index1 as i32);
         }
 
-        if !word(last_char) && word(Some(char as u32)) {
+        if !word(last_char, &separators) && word(Some(char as u32), &separators) {
             group_word_count += 1;
         }
 
         // ++++ -45 penalize extension
-        if last_char != None && last_char.unwrap() == penalty_lead {
-            scores[index1] += -45;
+        if last_char == Some(penalty_lead) {
+            scores[index1].add(Component::Extension, options.extension_penalty);
         }
 
-        if group_separator != None && group_separator.unwrap() == char {
+        // A group separator ends at this character
+        let group_end: bool = group_separators.iter().any(|sep| {
+            sep.len() <= index1 + 1 && chars[index1 + 1 - sep.len()..=index1] == sep[..]
+        });
+        if group_end {
             group_alist[0][1] = group_word_count;
             group_word_count = 0;
             group_alist.insert(0, vec![index1 as i32, group_word_count]);
@@ -177,8 +718,6 @@ pub fn get_heatmap_str(scores: &mut Vec<i32>, str: &str, group_separator: Option
         } else {
             last_char = Some(char as u32);
         }
-
-        index1 += 1;
     }
 
     let group_count: i32 = group_alist.len() as i32;
@@ -186,15 +725,26 @@ pub fn get_heatmap_str(scores: &mut Vec<i32>, str: &str, group_separator: Option
 
     // ++++ slash group-count penalty
     if separator_count != 0 {
-        inc_vec(scores, Some(group_count * -2), None, None);
+        inc_vec(
+            &mut scores,
+            Component::GroupCount,
+            Some(group_count * GROUP_COUNT_PENALTY),
+            None,
+            None,
+        );
     }
 
     let mut index2: i32 = separator_count;
     let mut last_group_limit: Option<i32> = None;
     let mut basepath_found: bool = false;
+    let mut segmentation: Segmentation = Segmentation::default();
+
+    // Groups run from last to first, so the first group with a word is
+    // the last one listed
+    let first_word_group: Option<usize> = group_alist.iter().rposition(|group| 2 < group.len());
 
     // score each group further
-    for group in group_alist {
+    for (position, group) in group_alist.into_iter().enumerate() {
         let group_start: i32 = group[0];
         let word_count: i32 = group[1];
         // this is the number of effective word groups
@@ -203,6 +753,9 @@ pub fn get_heatmap_str(scores: &mut Vec<i32>, str: &str, group_separator: Option
 
         if words_length != 0 && !basepath_found {
             basepath_found = true;
+            basepath_p = options.basepath_group != BasepathGroup::First;
+        }
+        if options.basepath_group != BasepathGroup::Last && Some(position) == first_word_group {
             basepath_p = true;
         }
 
@@ -213,40 +766,63 @@ pub fn get_heatmap_str(scores: &mut Vec<i32>, str: &str, group_separator: Option
             if separator_count > 1 {
                 boosts = separator_count - 1;
             }
+            if let Some(cap) = options.basepath_separator_boost_cap {
+                boosts = min(boosts, cap);
+            }
             // ++++ basepath word count penalty
-            let penalty: i32 = -word_count;
-            num = 35 + boosts + penalty;
+            let penalty: i32 = (-word_count).saturating_mul(options.basepath_word_penalty);
+            num = options
+                .basepath_bonus
+                .saturating_add(boosts)
+                .saturating_add(penalty);
         }
         // ++++ non-basepath penalties
         else {
             if index2 == 0 {
-                num = -3;
+                num = FIRST_GROUP_PENALTY;
             } else {
-                num = -5 + ((index2 as i32) - 1);
+                num = NON_BASEPATH_PENALTY + ((index2 as i32) - 1);
             }
         }
 
-        inc_vec(scores, Some(num), Some(group_start + 1), last_group_limit);
+        let component: Component = if basepath_p {
+            Component::Basepath
+        } else {
+            Component::NonBasepath
+        };
+        inc_vec(
+            &mut scores,
+            component,
+            Some(num),
+            Some(group_start + 1),
+            last_group_limit,
+        );
+
+        let group_range: Range<usize> =
+            (group_start + 1) as usize..last_group_limit.unwrap_or(str_len as i32) as usize;
+        if basepath_p && segmentation.basepath.is_none() {
+            segmentation.basepath = Some(group_range.clone());
+        }
+        segmentation.groups.insert(0, group_range);
 
         let mut cddr_group: Vec<i32> = group.clone();
         cddr_group.remove(0);
         cddr_group.remove(0);
-        let mut word_index: i32 = (words_length - 1) as i32;
-        let mut last_word: i32 = if last_group_limit != None {
-            last_group_limit.unwrap()
-        } else {
-            str_len as i32
-        };
+        let mut word_index: i32 = words_length as i32 - 1;
+        let mut last_word: i32 = last_group_limit.unwrap_or(str_len as i32);
 
         for word in cddr_group {
+            segmentation.word_starts.push(word as usize);
+
             // ++++  beg word bonus AND
-            scores[word as usize] += 85;
+            scores[word as usize].add(Component::WordStart, options.word_start_bonus);
 
             let mut index3: i32 = word;
             let mut char_i: i32 = 0;
             while index3 < last_word {
-                scores[index3 as usize] += (-3 * word_index) -  // ++++ word order penalty
-                    char_i; // ++++ char order penalty
+                let cell: &mut C = &mut scores[index3 as usize];
+                cell.add(Component::WordOrder, WORD_ORDER_PENALTY * word_index); // ++++ word order penalty
+                cell.add(Component::CharOrder, CHAR_ORDER_PENALTY * char_i); // ++++ char order penalty
                 char_i += 1;
                 index3 += 1;
             }
@@ -258,6 +834,184 @@ pub fn get_heatmap_str(scores: &mut Vec<i32>, str: &str, group_separator: Option
         last_group_limit = Some(group_start + 1);
         index2 -= 1;
     }
+
+    segmentation.word_starts.sort_unstable();
+    return (scores, segmentation);
+}
+
+/// Prepend a prefix of LEN characters, like `~/`, to the heatmap SCORES
+/// and SEGMENTATION.
+///
+/// The prefix gets the baseline score DEFAULT-SCORE and a group of its
+/// own, and everything else is shifted along unchanged.
+fn prefixed<C: Cell>(
+    mut scores: Vec<C>,
+    segmentation: Segmentation,
+    len: usize,
+    default_score: i32,
+) -> (Vec<C>, Segmentation) {
+    let prefix = std::iter::repeat_n(C::new(Component::Base, default_score), len);
+    scores.splice(0..0, prefix);
+
+    let shift = |range: Range<usize>| range.start + len..range.end + len;
+    let mut groups: Vec<Range<usize>> = segmentation.groups.into_iter().map(shift).collect();
+    groups.insert(0, 0..len);
+    let segmentation: Segmentation = Segmentation {
+        groups,
+        word_starts: segmentation
+            .word_starts
+            .iter()
+            .map(|index| index + len)
+            .collect(),
+        basepath: segmentation.basepath.map(shift),
+    };
+    return (scores, segmentation);
+}
+
+/// Return the length of the URL scheme at the start of CHARS, `://`
+/// included.
+fn url_scheme_len(chars: &[char]) -> Option<usize> {
+    let colon: usize = chars.iter().position(|&ch| ch == ':')?;
+    let valid: bool = chars.first()?.is_ascii_alphabetic()
+        && chars[..colon]
+            .iter()
+            .all(|&ch| ch.is_ascii_alphanumeric() || ch == '+' || ch == '-' || ch == '.');
+    if valid && chars[colon..].starts_with(&[':', '/', '/']) {
+        return Some(colon + 3);
+    }
+    return None;
+}
+
+/// Return the character range of the basepath in STR.
+///
+/// The basepath is the last group split by GROUP-SEPARATOR that contains
+/// a word, exactly as `get_heatmap_str` weights it.
+///
+/// # Arguments
+///
+/// * `str` - The string to inspect.
+/// * `group_separator` - Character that splits the string into groups.
+pub fn basepath(str: &str, group_separator: Option<char>) -> Option<Range<usize>> {
+    return get_heatmap_str(str, group_separator).segmentation.basepath;
+}
+
+/// Return STR with `.` components and duplicate separators removed.
+///
+/// With RESOLVE-PARENT, `..` components also cancel the component before
+/// them, without touching the file system. Leading and trailing
+/// separators are kept, and components are joined with the first
+/// separator found in STR.
+///
+/// # Arguments
+///
+/// * `str` - The path to normalize.
+/// * `resolve_parent` - Resolve `..` components lexically.
+pub fn normalize_path(str: &str, resolve_parent: bool) -> String {
+    let separator: char = str.chars().find(is_path_separator).unwrap_or('/');
+    let absolute: bool = str.starts_with(|ch: char| is_path_separator(&ch));
+    let trailing: bool = str.ends_with(|ch: char| is_path_separator(&ch));
+
+    let mut components: Vec<&str> = Vec::new();
+    for component in str.split(|ch: char| is_path_separator(&ch)) {
+        match component {
+            "" | "." => {}
+            ".." if resolve_parent => match components.last() {
+                Some(&last) if last != ".." => {
+                    components.pop();
+                }
+                None if absolute => {}
+                _ => components.push(component),
+            },
+            _ => components.push(component),
+        }
+    }
+
+    let mut normalized: String = String::new();
+    if absolute {
+        normalized.push(separator);
+    }
+    normalized.push_str(&components.join(&separator.to_string()));
+    if trailing && !components.is_empty() {
+        normalized.push(separator);
+    }
+    if normalized.is_empty() && !str.is_empty() {
+        normalized.push('.');
+    }
+    return normalized;
+}
+
+/// Return the index of the penalty-lead `.` that starts the extension.
+///
+/// The extension belongs to the last path component of the basepath, so
+/// the search stops at a path separator. A leading `.` of the basepath or
+/// of a component names a dotfile rather than an extension.
+fn extension_start(chars: &[char], basepath: Option<Range<usize>>) -> Option<usize> {
+    let range: Range<usize> = basepath?;
+    let start: usize = range.start;
+    for index in range.rev() {
+        if is_path_separator(&chars[index]) {
+            return None;
+        }
+        if chars[index] == PENALTY_LEAD {
+            let leading: bool = index == start || is_path_separator(&chars[index - 1]);
+            return if leading { None } else { Some(index) };
+        }
+    }
+    return None;
+}
+
+/// Check if CH separates path components.
+pub(crate) fn is_path_separator(ch: &char) -> bool {
+    return *ch == '/' || *ch == '\\';
+}
+
+/// Add AMOUNT of COMPONENT to the score of RESULT, noting it in PARTS.
+fn adjust(
+    result: &mut Result,
+    parts: &mut Option<&mut Vec<(Component, i32)>>,
+    component: Component,
+    amount: i32,
+) {
+    if amount == 0 {
+        return;
+    }
+    result.score += amount;
+    if let Some(parts) = parts {
+        parts.push((component, amount));
+    }
+}
+
+/// Check if CH is invisible in text, like a soft hyphen or a zero-width
+/// joiner.
+fn is_invisible(ch: char) -> bool {
+    return matches!(
+        ch,
+        '\u{ad}' | '\u{180e}' | '\u{200b}'..='\u{200f}' | '\u{2060}'..='\u{2064}' | '\u{feff}'
+    );
+}
+
+/// Return the indices of the first characters of the path components.
+fn component_starts(chars: &[char]) -> Vec<usize> {
+    return (0..chars.len())
+        .filter(|&index| {
+            !is_path_separator(&chars[index])
+                && (index == 0 || is_path_separator(&chars[index - 1]))
+        })
+        .collect();
+}
+
+/// Return the indices of the leading dots of hidden path components.
+fn hidden_dots(chars: &[char]) -> Vec<usize> {
+    let mut dots: Vec<usize> = Vec::new();
+    let mut start: usize = 0;
+    for component in chars.split(is_path_separator) {
+        let hidden: bool = component.first() == Some(&'.') && component.iter().any(|&ch| ch != '.');
+        if hidden {
+            dots.push(start);
+        }
+        start += component.len() + 1;
+    }
+    return dots;
 }
 
 /// Return sublist bigger than VAL from sorted SORTED-LIST.
@@ -268,12 +1022,11 @@ fn bigger_sublist(
     sorted_list: Option<&VecDeque<Option<u32>>>,
     val: Option<u32>,
 ) {
-    if sorted_list == None {
-        return;
-    }
-    let sl: &VecDeque<Option<u32>> = sorted_list.unwrap();
-    if val != None {
-        let v: u32 = val.unwrap();
+    let sl: &VecDeque<Option<u32>> = match sorted_list {
+        Some(sl) => sl,
+        None => return,
+    };
+    if let Some(v) = val {
         for sub in sl {
             if sub.unwrap() > v {
                 result.push_back(Some(sub.unwrap()));
@@ -286,56 +1039,220 @@ fn bigger_sublist(
     }
 }
 
+/// Quality of a match, from the strongest to the weakest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MatchKind {
+    /// The query matches every character of the string.
+    Exact,
+    /// The query matches a contiguous run at the start of the string.
+    Prefix,
+    /// Every contiguous run of matched characters starts a word.
+    WordBoundary,
+    /// Any other subsequence match.
+    Fuzzy,
+}
+
 #[derive(Debug, Clone)]
 pub struct Result {
     pub indices: Vec<i32>,
-    pub score: i32,
+    pub score: Score,
     pub tail: i32,
+    pub kind: MatchKind,
+    /// Length of the longest run of contiguous matched characters.
+    pub longest_run: i32,
+    /// Number of gaps between matched characters.
+    pub gap_count: i32,
+    /// Index of the first matched character, or -1 without any.
+    pub first_match_index: i32,
+    /// Whether the query matched within the filename alone, under
+    /// `ScoreOptions::filename_first`.
+    pub filename_match: bool,
 }
 
 impl Result {
-    pub fn new(indices: Vec<i32>, score: i32, tail: i32) -> Result {
+    pub fn new(indices: Vec<i32>, score: i64, tail: i32) -> Result {
+        let mut longest_run: i32 = 0;
+        let mut gap_count: i32 = 0;
+        let mut run: i32 = 0;
+        for (n, &idx) in indices.iter().enumerate() {
+            if 0 < n && indices[n - 1] + 1 != idx {
+                gap_count += 1;
+                run = 0;
+            }
+            run += 1;
+            longest_run = longest_run.max(run);
+        }
+        let first_match_index: i32 = indices.first().copied().unwrap_or(-1);
         Result {
             indices,
-            score,
+            score: Score(score),
             tail,
+            kind: MatchKind::Fuzzy,
+            longest_run,
+            gap_count,
+            first_match_index,
+            filename_match: false,
+        }
+    }
+
+    /// Return the matched characters as ranges of adjacent indices.
+    ///
+    /// UIs can then emit one span per run instead of one per character.
+    pub fn ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for &index in &self.indices {
+            let index: usize = index as usize;
+            match ranges.last_mut() {
+                Some(last) if last.end == index => last.end += 1,
+                _ => ranges.push(index..index + 1),
+            }
+        }
+        return ranges;
+    }
+}
+
+/// Classify the match INDICES against STR.
+///
+/// # Arguments
+///
+/// * `len` - Number of characters that take part in matching.
+/// * `indices` - Sorted indices of the matched characters.
+/// * `word_starts` - Sorted indices where a word starts.
+fn match_kind(len: usize, indices: &[i32], word_starts: &[usize]) -> MatchKind {
+    if indices.len() == len {
+        return MatchKind::Exact;
+    }
+    let contiguous = indices.windows(2).all(|w| w[1] == w[0] + 1);
+    if contiguous && indices[0] == 0 {
+        return MatchKind::Prefix;
+    }
+    let at_word_starts = indices.iter().enumerate().all(|(n, &idx)| {
+        if 0 < n && indices[n - 1] + 1 == idx {
+            return true;
+        }
+        return word_starts.binary_search(&(idx as usize)).is_ok();
+    });
+    if at_word_starts {
+        return MatchKind::WordBoundary;
+    }
+    return MatchKind::Fuzzy;
+}
+
+/// Score the alignment INDICES over HEATMAP the way the search does, with
+/// CONTIGUOUS-BASE as the bonus for adjacent characters.
+fn aligned_result(heatmap: &[i32], indices: &[usize], contiguous_base: i32) -> Result {
+    let mut score: i64 = 0;
+    let mut tail: i32 = 0;
+    for (position, &index) in indices.iter().enumerate().rev() {
+        score += heatmap[index] as i64;
+        match indices.get(position + 1) {
+            Some(&next) if next == index + 1 => {
+                score += (min(tail, CONTIGUOUS_BONUS_CAP) * CONTIGUOUS_BONUS) as i64
+                    + contiguous_base as i64;
+                tail += 1;
+            }
+            _ => tail = 0,
         }
     }
+    let indices: Vec<i32> = indices.iter().map(|&index| index as i32).collect();
+    return Result::new(indices, score, tail);
+}
+
+/// Return the optimal alignment of QUERY in the string described by
+/// STR-INFO and HEATMAP.
+///
+/// The score is the raw alignment score, before `score` adds its
+/// full-match boost.
+pub fn best_match(str_info: &StrInfo, heatmap: &Heatmap, query: &str) -> Option<Result> {
+    if query.is_empty() {
+        return None;
+    }
+    let mut match_cache: HashMap<u32, Vec<Result>> = HashMap::new();
+    let mut optimal_match: Vec<Result> = Vec::new();
+    find_best_match(
+        &mut optimal_match,
+        str_info,
+        &heatmap.scores,
+        None,
+        query,
+        query.chars().count() as i32,
+        0,
+        &mut match_cache,
+    );
+    return optimal_match.into_iter().next();
 }
 
 /// Recursively compute the best match for a string, passed as STR-INFO and
 /// HEATMAP, according to QUERY.
+///
+/// This is the internal building block behind `best_match`; the recursion
+/// state (`greater_than`, `q_index` and the cache) must start out as
+/// `None`, `0` and empty.
+#[doc(hidden)]
 pub fn find_best_match(
     imatch: &mut Vec<Result>,
-    str_info: HashMap<Option<u32>, VecDeque<Option<u32>>>,
-    heatmap: Vec<i32>,
+    str_info: &StrInfo,
+    heatmap: &[i32],
     greater_than: Option<u32>,
     query: &str,
     query_length: i32,
     q_index: i32,
     match_cache: &mut HashMap<u32, Vec<Result>>,
 ) {
-    let greater_num: u32 = if greater_than != None {
-        greater_than.unwrap()
-    } else {
-        0
-    };
+    let query: Vec<char> = query.chars().collect();
+    find_best_match_gapped(
+        imatch,
+        str_info,
+        heatmap,
+        greater_than,
+        &query,
+        query_length,
+        q_index,
+        match_cache,
+        None,
+        1,
+        CONTIGUOUS_BASE,
+    );
+}
+
+/// Like `find_best_match`, but skip no more than MAX-GAP characters
+/// between two consecutive query characters. QUERY comes decoded, so each
+/// level indexes its character directly.
+fn find_best_match_gapped(
+    imatch: &mut Vec<Result>,
+    str_info: &StrInfo,
+    heatmap: &[i32],
+    greater_than: Option<u32>,
+    query: &[char],
+    query_length: i32,
+    q_index: i32,
+    match_cache: &mut HashMap<u32, Vec<Result>>,
+    max_gap: Option<u32>,
+    keep: usize,
+    contiguous_base: i32,
+) {
+    let greater_num: u32 = greater_than.unwrap_or(0);
     let hash_key: u32 = q_index as u32 + (greater_num * query_length as u32);
     let hash_value: Option<&Vec<Result>> = match_cache.get(&hash_key);
 
-    if !hash_value.is_none() {
+    if let Some(cached) = hash_value {
         // Process match_cache here
         imatch.clear();
-        for val in hash_value.unwrap() {
+        for val in cached {
             imatch.push(val.clone());
         }
     } else {
-        let uchar: Option<u32> = Some(query.chars().nth(q_index as usize).unwrap() as u32);
-        let sorted_list: Option<&VecDeque<Option<u32>>> = str_info.get(&uchar);
+        let uchar: Option<u32> = Some(query[q_index as usize] as u32);
+        let sorted_list: Option<&VecDeque<Option<u32>>> = str_info.table.get(&uchar);
         let mut indexes: VecDeque<Option<u32>> = VecDeque::new();
         bigger_sublist(&mut indexes, sorted_list, greater_than);
-        let mut temp_score: i32;
-        let mut best_score: i32 = std::f32::NEG_INFINITY as i32;
+        if let (Some(gap), Some(last)) = (max_gap, greater_than) {
+            // The first query character is free to start anywhere
+            if 0 < q_index {
+                indexes.retain(|index| index.unwrap() <= last + 1 + gap);
+            }
+        }
+        let mut temp_score: Score;
 
         if q_index >= query_length - 1 {
             // At the tail end of the recursion, simply generate all possible
@@ -344,49 +1261,71 @@ pub fn find_best_match(
                 let mut indices: Vec<i32> = Vec::new();
                 let idx: i32 = index.unwrap() as i32;
                 indices.push(idx);
-                imatch.push(Result::new(indices, heatmap[idx as usize], 0));
+                imatch.push(Result::new(indices, heatmap[idx as usize] as i64, 0));
             }
         } else {
             for index in indexes {
                 let idx: i32 = index.unwrap() as i32;
                 let mut elem_group: Vec<Result> = Vec::new();
-                find_best_match(
+                find_best_match_gapped(
                     &mut elem_group,
-                    str_info.clone(),
-                    heatmap.clone(),
+                    str_info,
+                    heatmap,
                     Some(idx as u32),
                     query,
                     query_length,
                     q_index + 1,
                     match_cache,
+                    max_gap,
+                    keep,
+                    contiguous_base,
                 );
 
                 for elem in elem_group {
                     let caar: i32 = elem.indices[0];
-                    let cadr: i32 = elem.score;
+                    let cadr: Score = elem.score;
                     let cddr: i32 = elem.tail;
 
                     if (caar - 1) == idx {
                         temp_score = cadr + heatmap[idx as usize] +
-                            (min(cddr, 3) * 15) +  // boost contiguous matches
-                            60;
+                            (min(cddr, CONTIGUOUS_BONUS_CAP) * CONTIGUOUS_BONUS) +  // boost contiguous matches
+                            contiguous_base;
                     } else {
                         temp_score = cadr + heatmap[idx as usize];
                     }
 
-                    // We only care about the optimal match, so only forward the match
-                    // with the best score to parent
-                    if temp_score > best_score {
-                        best_score = temp_score;
-
-                        imatch.clear();
+                    // We only care about the optimal matches, so only forward the
+                    // KEEP best to parent; the first one found wins a tie
+                    let rank: usize = imatch
+                        .iter()
+                        .position(|kept| kept.score < temp_score)
+                        .unwrap_or(imatch.len());
+                    if rank < keep {
                         let mut indices: Vec<i32> = elem.indices.clone();
                         indices.insert(0, idx);
                         let mut tail: i32 = 0;
+                        let mut gap_count: i32 = elem.gap_count;
                         if (caar - 1) == idx {
                             tail = cddr + 1;
+                        } else {
+                            gap_count += 1;
                         }
-                        imatch.push(Result::new(indices, temp_score, tail));
+                        // The leading run grows with `tail`, so the stats
+                        // carry over from the sub-match without a rescan.
+                        imatch.insert(
+                            rank,
+                            Result {
+                                indices,
+                                score: temp_score,
+                                tail,
+                                kind: MatchKind::Fuzzy,
+                                longest_run: max(elem.longest_run, tail + 1),
+                                gap_count,
+                                first_match_index: idx,
+                                filename_match: false,
+                            },
+                        );
+                        imatch.truncate(keep);
                     }
                 }
             }
@@ -397,42 +1336,980 @@ pub fn find_best_match(
     }
 }
 
-/// Return best score matching QUERY against STR.
-pub fn score(str: &str, query: &str) -> Option<Result> {
-    if str.is_empty() || query.is_empty() {
-        return None;
-    }
-    let mut str_info: HashMap<Option<u32>, VecDeque<Option<u32>>> = HashMap::new();
-    get_hash_for_string(&mut str_info, str);
-
-    let mut heatmap: Vec<i32> = Vec::new();
-    get_heatmap_str(&mut heatmap, str, None);
-
-    let query_length: i32 = query.chars().count() as i32;
-    let full_match_boost: bool = (1 < query_length) && (query_length < 5);
-    let mut match_cache: HashMap<u32, Vec<Result>> = HashMap::new();
-    let mut optimal_match: Vec<Result> = Vec::new();
-    find_best_match(
-        &mut optimal_match,
+/// Compute the best matches of QUERY in STR-INFO from its first character,
+/// with the matcher chosen at build time.
+///
+/// The iterative matcher is the default; the `recursive-search` feature
+/// switches back to the recursion of `find_best_match`. Tests build both
+/// under every feature set and compare them directly.
+fn best_matches(
+    imatch: &mut Vec<Result>,
+    str_info: &StrInfo,
+    heatmap: &[i32],
+    greater_than: Option<u32>,
+    query: &str,
+    query_length: i32,
+    match_cache: &mut HashMap<u32, Vec<Result>>,
+    max_gap: Option<u32>,
+    keep: usize,
+    contiguous_base: i32,
+) {
+    #[cfg(feature = "recursive-search")]
+    find_best_match_gapped(
+        imatch,
         str_info,
         heatmap,
-        None,
+        greater_than,
+        &query.chars().collect::<Vec<char>>(),
+        query_length,
+        0,
+        match_cache,
+        max_gap,
+        keep,
+        contiguous_base,
+    );
+    #[cfg(not(feature = "recursive-search"))]
+    find_best_match_iterative(
+        imatch,
+        str_info,
+        heatmap,
+        greater_than,
         query,
         query_length,
+        match_cache,
+        max_gap,
+        keep,
+        contiguous_base,
+    );
+}
+
+/// Compute what `find_best_match_gapped` returns from the first query
+/// character, bottom-up instead of recursively.
+///
+/// The matches after each reachable index of the previous query character
+/// are filled into MATCH-CACHE from the last query character backwards,
+/// under the keys the recursion uses, and merged in the same order, so the
+/// results and their ties come out identical.
+#[cfg(any(test, not(feature = "recursive-search")))]
+fn find_best_match_iterative(
+    imatch: &mut Vec<Result>,
+    str_info: &StrInfo,
+    heatmap: &[i32],
+    greater_than: Option<u32>,
+    query: &str,
+    query_length: i32,
+    match_cache: &mut HashMap<u32, Vec<Result>>,
+    max_gap: Option<u32>,
+    keep: usize,
+    contiguous_base: i32,
+) {
+    let no_indexes: VecDeque<Option<u32>> = VecDeque::new();
+    let needles: Vec<&VecDeque<Option<u32>>> = query
+        .chars()
+        .map(|ch| str_info.table.get(&Some(ch as u32)).unwrap_or(&no_indexes))
+        .collect();
+
+    // Find the indexes each query character can take after the ones
+    // before it, so only those are filled below. A character without any
+    // means there is no match at all.
+    let mut reachable: Vec<Vec<u32>> = Vec::with_capacity(needles.len());
+    for needle in &needles {
+        let indexes: Vec<u32> = needle
+            .iter()
+            .map(|index| index.unwrap())
+            .filter(|&index| match reachable.last() {
+                None => greater_than.is_none_or(|last| last < index),
+                Some(previous) => {
+                    // The closest earlier index leaves the smallest gap
+                    let before: usize = previous.partition_point(|&last| last < index);
+                    0 < before && max_gap.is_none_or(|gap| index <= previous[before - 1] + 1 + gap)
+                }
+            })
+            .collect();
+        if indexes.is_empty() {
+            imatch.clear();
+            return;
+        }
+        reachable.push(indexes);
+    }
+
+    for q_index in (1..query_length).rev() {
+        for &previous in &reachable[q_index as usize - 1] {
+            let hash_key: u32 = q_index as u32 + (previous * query_length as u32);
+            let matches: Vec<Result> = match_step(
+                &needles,
+                heatmap,
+                Some(previous),
+                query_length,
+                q_index,
+                match_cache,
+                max_gap,
+                keep,
+                contiguous_base,
+            );
+            match_cache.insert(hash_key, matches);
+        }
+    }
+    *imatch = match_step(
+        &needles,
+        heatmap,
+        greater_than,
+        query_length,
         0,
-        &mut match_cache,
+        match_cache,
+        max_gap,
+        keep,
+        contiguous_base,
     );
+}
 
-    if optimal_match.is_empty() {
-        return None;
+/// Return the matches of the query from Q-INDEX on, after GREATER-THAN.
+///
+/// The matches from Q-INDEX + 1 on must already be in MATCH-CACHE.
+#[cfg(any(test, not(feature = "recursive-search")))]
+fn match_step(
+    needles: &[&VecDeque<Option<u32>>],
+    heatmap: &[i32],
+    greater_than: Option<u32>,
+    query_length: i32,
+    q_index: i32,
+    match_cache: &HashMap<u32, Vec<Result>>,
+    max_gap: Option<u32>,
+    keep: usize,
+    contiguous_base: i32,
+) -> Vec<Result> {
+    let mut indexes: VecDeque<Option<u32>> = VecDeque::new();
+    bigger_sublist(&mut indexes, Some(needles[q_index as usize]), greater_than);
+    if let (Some(gap), Some(last)) = (max_gap, greater_than) {
+        // The first query character is free to start anywhere
+        if 0 < q_index {
+            indexes.retain(|index| index.unwrap() <= last + 1 + gap);
+        }
+    }
+
+    let mut imatch: Vec<Result> = Vec::new();
+    if q_index >= query_length - 1 {
+        for index in indexes {
+            let idx: i32 = index.unwrap() as i32;
+            imatch.push(Result::new(vec![idx], heatmap[idx as usize] as i64, 0));
+        }
+        return imatch;
+    }
+    for index in indexes {
+        let idx: i32 = index.unwrap() as i32;
+        let hash_key: u32 = (q_index + 1) as u32 + (idx as u32 * query_length as u32);
+        for elem in &match_cache[&hash_key] {
+            let caar: i32 = elem.indices[0];
+            let cadr: Score = elem.score;
+            let cddr: i32 = elem.tail;
+            let contiguous: bool = (caar - 1) == idx;
+
+            let mut temp_score: Score = cadr + heatmap[idx as usize];
+            if contiguous {
+                temp_score += min(cddr, CONTIGUOUS_BONUS_CAP) * CONTIGUOUS_BONUS;
+                temp_score += contiguous_base;
+            }
+
+            // Same ranking as the recursion: the first one found wins a tie
+            let rank: usize = imatch
+                .iter()
+                .position(|kept| kept.score < temp_score)
+                .unwrap_or(imatch.len());
+            if rank < keep {
+                let mut indices: Vec<i32> = Vec::with_capacity(elem.indices.len() + 1);
+                indices.push(idx);
+                indices.extend_from_slice(&elem.indices);
+                let tail: i32 = if contiguous { cddr + 1 } else { 0 };
+                let gap_count: i32 = elem.gap_count + if contiguous { 0 } else { 1 };
+                imatch.insert(
+                    rank,
+                    Result {
+                        indices,
+                        score: temp_score,
+                        tail,
+                        kind: MatchKind::Fuzzy,
+                        longest_run: max(elem.longest_run, tail + 1),
+                        gap_count,
+                        first_match_index: idx,
+                        filename_match: false,
+                    },
+                );
+                imatch.truncate(keep);
+            }
+        }
+    }
+    return imatch;
+}
+
+/// Return best score matching QUERY against STR.
+pub fn score(str: &str, query: &str) -> Option<Result> {
+    return score_with_options(str, query, &ScoreOptions::default());
+}
+
+/// Return best score matching QUERY against STR, weighted by OPTIONS.
+pub fn score_with_options(str: &str, query: &str, options: &ScoreOptions) -> Option<Result> {
+    return score_query(str, &Query::new(query).options(options.clone()));
+}
+
+/// Return best score matching the compiled QUERY against STR.
+pub fn score_query(str: &str, query: &Query) -> Option<Result> {
+    return try_score_query(str, query).unwrap_or(None);
+}
+
+/// Return best score matching QUERY against STR, or why the input was
+/// rejected.
+pub fn try_score(str: &str, query: &str) -> std::result::Result<Option<Result>, FlxError> {
+    return try_score_query(str, &Query::new(query));
+}
+
+/// Return best score matching the compiled QUERY against STR, or why the
+/// input was rejected.
+pub fn try_score_query(str: &str, query: &Query) -> std::result::Result<Option<Result>, FlxError> {
+    let prepared: Prepared = Prepared::new(str, &query.options);
+    let str_info: StrInfo = prepared.str_info(query.case_mode.resolve(query.text()));
+    return prepared.score(query, &str_info);
+}
+
+/// Return up to N distinct alignments of the compiled QUERY in STR, the
+/// best first.
+///
+/// The first is the match `score_query` returns; the rest are the
+/// runners-up of a wider search that keeps several matches per state,
+/// useful when the best highlight looks unnatural and a more contiguous
+/// alternative reads better. The runners-up are ordered by the score of
+/// that search, before the adjustments that `ScoreOptions` adds on top, so
+/// they may score higher than the first.
+pub fn score_n_best(str: &str, query: &Query, n: usize) -> Vec<Result> {
+    let prepared: Prepared = Prepared::new(str, &query.options);
+    let str_info: StrInfo = prepared.str_info(query.case_mode.resolve(query.text()));
+    return prepared
+        .search(query, &str_info, n, None)
+        .unwrap_or_default();
+}
+
+/// Return the best score matching QUERY against each of CANDIDATES, in
+/// input order.
+///
+/// Unlike calling `score` in a loop, the query is compiled and its case
+/// mode resolved once, and the character, heatmap, occurrence table and
+/// search cache buffers are cleared and reused from one candidate to the
+/// next instead of allocated afresh. See `Candidate` to keep the heatmap
+/// and occurrence table of a candidate across queries.
+pub fn score_many(candidates: &[&str], query: &str) -> Vec<Option<Result>> {
+    return score_many_query(candidates, &Query::new(query));
+}
+
+/// Like `score_many`, for a compiled QUERY.
+pub fn score_many_query(candidates: &[&str], query: &Query) -> Vec<Option<Result>> {
+    let case_mode: CaseMode = query.case_mode.resolve(query.text());
+    let mut scratch: Scratch = Scratch::default();
+    return candidates
+        .iter()
+        .map(|candidate| {
+            let prepared: Prepared = Prepared::new_in(candidate, &query.options, &mut scratch);
+            let str_info: StrInfo = prepared.str_info_in(case_mode, &mut scratch);
+            let result: Option<Result> = prepared
+                .search_in(query, &str_info, 1, None, &mut scratch.match_cache)
+                .ok()
+                .and_then(|matches| matches.into_iter().next());
+            str_info.recycle(&mut scratch);
+            prepared.recycle(&mut scratch);
+            result
+        })
+        .collect();
+}
+
+/// Buffers that scoring one string after another can reuse, so a batch
+/// doesn't allocate them afresh for each string.
+#[derive(Debug, Default)]
+pub(crate) struct Scratch {
+    chars: Vec<char>,
+    heatmap: Vec<i32>,
+    table: HashMap<Option<u32>, VecDeque<Option<u32>>>,
+    match_cache: HashMap<u32, Vec<Result>>,
+}
+
+/// The part of scoring that depends on the string and options only, so it
+/// can be shared by every query matched against the same string.
+#[derive(Debug, Clone)]
+pub(crate) struct Prepared<'a> {
+    text: Cow<'a, str>,
+    /// Index in the original string of each character of `text`, when
+    /// invisible characters were left out.
+    positions: Option<Vec<usize>>,
+    chars: Vec<char>,
+    heatmap: Vec<i32>,
+    segmentation: Segmentation,
+    extension: Option<usize>,
+    match_len: usize,
+}
+
+impl<'a> Prepared<'a> {
+    /// Normalize STR and build its heatmap as OPTIONS ask.
+    pub(crate) fn new(str: &'a str, options: &ScoreOptions) -> Prepared<'a> {
+        return Prepared::new_in(str, options, &mut Scratch::default());
     }
 
-    let mut result_1: Result = optimal_match[0].clone();
-    let caar: usize = result_1.indices.len();
+    /// Like `new`, but build into the buffers of SCRATCH. Hand them back
+    /// with `recycle` once the preparation is no longer needed.
+    pub(crate) fn new_in(
+        str: &'a str,
+        options: &ScoreOptions,
+        scratch: &mut Scratch,
+    ) -> Prepared<'a> {
+        let mut text: Cow<'a, str> = if options.normalize_path {
+            Cow::Owned(normalize_path(str, options.resolve_parent_dirs))
+        } else {
+            Cow::Borrowed(str)
+        };
+        let mut positions: Option<Vec<usize>> = None;
+        if options.skip_invisible && text.chars().any(is_invisible) {
+            positions = Some(
+                text.chars()
+                    .enumerate()
+                    .filter(|&(_, ch)| !is_invisible(ch))
+                    .map(|(index, _)| index)
+                    .collect(),
+            );
+            text = Cow::Owned(text.chars().filter(|&ch| !is_invisible(ch)).collect());
+        }
+        let mut chars: Vec<char> = std::mem::take(&mut scratch.chars);
+        chars.clear();
+        chars.extend(text.chars());
+        let (heatmap, segmentation) =
+            build_heatmap_in(&chars, options, std::mem::take(&mut scratch.heatmap));
+
+        // Only the characters before the extension take part in matching,
+        // but the heatmap still sees the whole string.
+        let extension: Option<usize> = extension_start(&chars, segmentation.basepath.clone());
+        let mut match_len: usize = chars.len();
+        if options.ignore_extension {
+            if let Some(ext) = extension {
+                match_len = ext;
+            }
+        }
+        return Prepared {
+            text,
+            positions,
+            chars,
+            heatmap,
+            segmentation,
+            extension,
+            match_len,
+        };
+    }
+
+    /// Hand the buffers back to SCRATCH, for the next `new_in`.
+    pub(crate) fn recycle(self, scratch: &mut Scratch) {
+        scratch.chars = self.chars;
+        scratch.heatmap = self.heatmap;
+    }
+
+    /// Take ownership of the string, so the preparation can be kept.
+    pub(crate) fn into_owned(self) -> Prepared<'static> {
+        return Prepared {
+            text: Cow::Owned(self.text.into_owned()),
+            positions: self.positions,
+            chars: self.chars,
+            heatmap: self.heatmap,
+            segmentation: self.segmentation,
+            extension: self.extension,
+            match_len: self.match_len,
+        };
+    }
 
-    if full_match_boost && caar == str.chars().count() {
-        result_1.score += 10000;
+    /// The string being matched, normalized if the options ask for it.
+    pub(crate) fn text(&self) -> &str {
+        return &self.text;
     }
 
-    return Some(result_1);
+    /// Point the indices of RESULT, found in `text`, back into the
+    /// original string.
+    pub(crate) fn restore(&self, result: &mut Result) {
+        if let Some(positions) = &self.positions {
+            for index in &mut result.indices {
+                *index = positions[*index as usize] as i32;
+            }
+            result.first_match_index = positions[result.first_match_index as usize] as i32;
+        }
+    }
+
+    /// Return the index in `text` of ORIGINAL, an index into the original
+    /// string.
+    pub(crate) fn position(&self, original: usize) -> usize {
+        return match &self.positions {
+            Some(positions) => positions
+                .binary_search(&original)
+                .unwrap_or_else(|index| index),
+            None => original,
+        };
+    }
+
+    /// Groups and word starts of the string.
+    pub(crate) fn segmentation(&self) -> &Segmentation {
+        return &self.segmentation;
+    }
+
+    /// Build the occurrence table of the matched characters for CASE-MODE.
+    pub(crate) fn str_info(&self, case_mode: CaseMode) -> StrInfo {
+        return self.str_info_in(case_mode, &mut Scratch::default());
+    }
+
+    /// Like `str_info`, but build into the table of SCRATCH. Hand it back
+    /// with `StrInfo::recycle`.
+    pub(crate) fn str_info_in(&self, case_mode: CaseMode, scratch: &mut Scratch) -> StrInfo {
+        let table: HashMap<Option<u32>, VecDeque<Option<u32>>> = std::mem::take(&mut scratch.table);
+        return StrInfo::build_in(&self.chars[..self.match_len], case_mode, table);
+    }
+
+    /// Match QUERY, using STR-INFO built for its resolved case mode.
+    pub(crate) fn score(
+        &self,
+        query: &Query,
+        str_info: &StrInfo,
+    ) -> std::result::Result<Option<Result>, FlxError> {
+        return self.score_parts(query, str_info, None);
+    }
+
+    /// Like `score`, and note every adjustment made after the search in
+    /// PARTS, when given.
+    pub(crate) fn score_parts(
+        &self,
+        query: &Query,
+        str_info: &StrInfo,
+        parts: Option<&mut Vec<(Component, i32)>>,
+    ) -> std::result::Result<Option<Result>, FlxError> {
+        let matches: Vec<Result> = self.search(query, str_info, 1, parts)?;
+        return Ok(matches.into_iter().next());
+    }
+
+    /// Return up to KEEP distinct matches of QUERY, the best first, with
+    /// the adjustments of the best noted in PARTS.
+    ///
+    /// The first is the match `score` returns. Keeping more than one match
+    /// per state lets the search reach other alignments, so the runners-up
+    /// come from a second, wider search and are ranked by its score, before
+    /// adjustments.
+    pub(crate) fn search(
+        &self,
+        query: &Query,
+        str_info: &StrInfo,
+        keep: usize,
+        parts: Option<&mut Vec<(Component, i32)>>,
+    ) -> std::result::Result<Vec<Result>, FlxError> {
+        let mut match_cache: HashMap<u32, Vec<Result>> = HashMap::new();
+        let mut matches: Vec<Result> =
+            self.search_in(query, str_info, 1, parts, &mut match_cache)?;
+        if keep <= 1 || matches.is_empty() {
+            return Ok(matches);
+        }
+        let runners_up: Vec<Result> =
+            self.search_in(query, str_info, keep, None, &mut match_cache)?;
+        for result in runners_up {
+            if matches.len() == keep {
+                break;
+            }
+            if result.indices != matches[0].indices {
+                matches.push(result);
+            }
+        }
+        return Ok(matches);
+    }
+
+    /// Like `search`, but reuse MATCH-CACHE as the search cache so its
+    /// buffers carry over from one string to the next.
+    pub(crate) fn search_in(
+        &self,
+        query: &Query,
+        str_info: &StrInfo,
+        keep: usize,
+        mut parts: Option<&mut Vec<(Component, i32)>>,
+        match_cache: &mut HashMap<u32, Vec<Result>>,
+    ) -> std::result::Result<Vec<Result>, FlxError> {
+        let options: &ScoreOptions = &query.options;
+        let word_starts_only: bool = query.word_starts_only;
+        let max_gap: Option<u32> = query.max_gap;
+        let lowered: String;
+        let query: &str = if query.case_mode == CaseMode::Insensitive {
+            lowered = query.text.to_lowercase();
+            &lowered
+        } else {
+            query.text()
+        };
+        if query.is_empty() {
+            return Err(FlxError::InvalidQuery("query is empty".to_string()));
+        }
+        if self.text.is_empty() || keep == 0 {
+            return Ok(Vec::new());
+        }
+        let chars: &[char] = &self.chars;
+        let heatmap: &Vec<i32> = &self.heatmap;
+        let segmentation: &Segmentation = &self.segmentation;
+        let match_len: usize = self.match_len;
+
+        // The match cache keys on `index * query_length`, which must not wrap
+        let limit: usize = u32::MAX as usize / query.chars().count();
+        if limit < chars.len() {
+            return Err(FlxError::CandidateTooLong {
+                length: chars.len(),
+                limit,
+            });
+        }
+
+        let mut str_info: Cow<StrInfo> = Cow::Borrowed(str_info);
+        if word_starts_only {
+            str_info
+                .to_mut()
+                .retain(|index| segmentation.word_starts.binary_search(&index).is_ok());
+        }
+
+        let query_length: i32 = query.chars().count() as i32;
+        match_cache.clear();
+        let mut optimal_match: Vec<Result> = Vec::new();
+
+        // Try the filename on its own before falling back to the whole path
+        let mut greater_than: Option<u32> = None;
+        if options.filename_first {
+            greater_than = chars[..match_len]
+                .iter()
+                .rposition(is_path_separator)
+                .map(|index| index as u32);
+        }
+        best_matches(
+            &mut optimal_match,
+            &str_info,
+            heatmap,
+            greater_than,
+            query,
+            query_length,
+            match_cache,
+            max_gap,
+            keep,
+            options.contiguous_base,
+        );
+        let filename_match: bool = options.filename_first && !optimal_match.is_empty();
+
+        if optimal_match.is_empty() && greater_than.is_some() {
+            match_cache.clear();
+            best_matches(
+                &mut optimal_match,
+                &str_info,
+                heatmap,
+                None,
+                query,
+                query_length,
+                match_cache,
+                max_gap,
+                keep,
+                options.contiguous_base,
+            );
+        }
+        // A single query character lists every occurrence
+        optimal_match.truncate(keep);
+
+        // Literal prefixes earn the prefix tier even when the optimal
+        // alignment lies elsewhere, like `ab` in `AB._B`
+        let mut prefix: Option<Result> = None;
+        if options.prefix_bonus != 0 && query_length as usize <= match_len {
+            let literal: bool = query.chars().enumerate().all(|(index, ch)| {
+                str_info
+                    .table
+                    .get(&Some(ch as u32))
+                    .is_some_and(|indexes| indexes.binary_search(&Some(index as u32)).is_ok())
+            });
+            if literal {
+                let indices: Vec<usize> = (0..query_length as usize).collect();
+                prefix = Some(aligned_result(heatmap, &indices, options.contiguous_base));
+            }
+        }
+
+        // Matches paired with whether they came from the initials attempt
+        let mut ranked: Vec<(Result, bool)> = optimal_match
+            .into_iter()
+            .map(|result| (result, false))
+            .collect();
+
+        // Try the initials of the path components on their own, like `sru`
+        // for `src/render/ui.rs`
+        if options.component_initials_bonus != 0 && 1 < query_length {
+            let starts: Vec<usize> = component_starts(&chars[..match_len]);
+            let mut initials_info: StrInfo = str_info.into_owned();
+            initials_info.retain(|index| starts.binary_search(&index).is_ok());
+            let mut initials_match: Vec<Result> = Vec::new();
+            match_cache.clear();
+            best_matches(
+                &mut initials_match,
+                &initials_info,
+                heatmap,
+                None,
+                query,
+                query_length,
+                match_cache,
+                max_gap,
+                keep,
+                options.contiguous_base,
+            );
+            for mut initials in initials_match {
+                initials.score += options.component_initials_bonus;
+                // The same alignment is only listed once, with its higher score
+                let same: Option<usize> = ranked
+                    .iter()
+                    .position(|kept| kept.0.indices == initials.indices);
+                if let Some(same) = same {
+                    if initials.score <= ranked[same].0.score {
+                        continue;
+                    }
+                    ranked.remove(same);
+                }
+                let rank: usize = ranked
+                    .iter()
+                    .position(|kept| kept.0.score < initials.score)
+                    .unwrap_or(ranked.len());
+                ranked.insert(rank, (initials, true));
+            }
+            ranked.truncate(keep);
+        }
+        if let Some(prefix) = prefix {
+            ranked.retain(|kept| kept.0.indices != prefix.indices);
+            ranked.insert(0, (prefix, false));
+            ranked.truncate(keep);
+        }
+
+        let mut matches: Vec<Result> = Vec::with_capacity(ranked.len());
+        for (result, initials) in ranked {
+            let mut parts: Option<&mut Vec<(Component, i32)>> = if matches.is_empty() {
+                parts.take()
+            } else {
+                None
+            };
+            if initials {
+                if let Some(parts) = &mut parts {
+                    parts.push((
+                        Component::ComponentInitials,
+                        options.component_initials_bonus,
+                    ));
+                }
+            }
+            // Only matches after the last separator lie in the filename
+            let in_filename: bool = greater_than.is_none_or(|last| last < result.indices[0] as u32);
+            let mut result: Result = self.finish(
+                result,
+                options,
+                query_length,
+                filename_match && !initials && in_filename,
+                &mut parts,
+            );
+            self.restore(&mut result);
+            matches.push(result);
+        }
+        return Ok(matches);
+    }
+
+    /// Score the alignment INDICES of a query as `search` scores it when it
+    /// picks that alignment.
+    ///
+    /// Alignments on component starts alone earn a positive
+    /// `component_initials_bonus`, and under `filename_first` those inside
+    /// the filename are marked as filename matches, before `finish`.
+    pub(crate) fn score_alignment(&self, indices: &[usize], options: &ScoreOptions) -> Result {
+        let query_length: i32 = indices.len() as i32;
+        let chars: &[char] = &self.chars[..self.match_len];
+        let mut result: Result = aligned_result(&self.heatmap, indices, options.contiguous_base);
+        let starts: Vec<usize> = component_starts(chars);
+        let initials: bool = 0 < options.component_initials_bonus
+            && 1 < query_length
+            && indices
+                .iter()
+                .all(|index| starts.binary_search(index).is_ok());
+        if initials {
+            result.score += options.component_initials_bonus;
+        }
+        let in_filename: bool = chars
+            .iter()
+            .rposition(is_path_separator)
+            .is_none_or(|last| last < indices[0]);
+        let mut result: Result = self.finish(
+            result,
+            options,
+            query_length,
+            options.filename_first && !initials && in_filename,
+            &mut None,
+        );
+        self.restore(&mut result);
+        return result;
+    }
+
+    /// Apply the adjustments made after the search to RESULT-1, a match of
+    /// a query of QUERY-LENGTH characters, noting them in PARTS.
+    pub(crate) fn finish(
+        &self,
+        mut result_1: Result,
+        options: &ScoreOptions,
+        query_length: i32,
+        filename_match: bool,
+        parts: &mut Option<&mut Vec<(Component, i32)>>,
+    ) -> Result {
+        let chars: &[char] = &self.chars;
+        let segmentation: &Segmentation = &self.segmentation;
+        let extension: Option<usize> = self.extension;
+        let match_len: usize = self.match_len;
+        let full_match_boost: bool = (FULL_MATCH_QUERY_LENGTH[0] <= query_length)
+            && (query_length <= FULL_MATCH_QUERY_LENGTH[1]);
+
+        let caar: usize = result_1.indices.len();
+
+        if full_match_boost && caar == match_len {
+            adjust(&mut result_1, parts, Component::FullMatch, FULL_MATCH_BOOST);
+        }
+
+        result_1.kind = match_kind(match_len, &result_1.indices, &segmentation.word_starts);
+        result_1.filename_match = filename_match;
+
+        // ++++ prefix tier and initialism bonuses
+        if matches!(result_1.kind, MatchKind::Exact | MatchKind::Prefix) {
+            adjust(
+                &mut result_1,
+                parts,
+                Component::PrefixTier,
+                options.prefix_bonus,
+            );
+        } else if 1 < query_length
+            && result_1.indices.iter().all(|&idx| {
+                segmentation
+                    .word_starts
+                    .binary_search(&(idx as usize))
+                    .is_ok()
+            })
+        {
+            adjust(
+                &mut result_1,
+                parts,
+                Component::Initialism,
+                options.initialism_bonus,
+            );
+        }
+
+        // ++++ hidden penalty, unless a leading dot was matched on purpose
+        if options.hidden_penalty != 0 {
+            let dots: Vec<usize> = hidden_dots(chars);
+            let queried: bool = result_1
+                .indices
+                .iter()
+                .any(|&idx| dots.contains(&(idx as usize)));
+            if !dots.is_empty() && !queried {
+                adjust(
+                    &mut result_1,
+                    parts,
+                    Component::Hidden,
+                    options.hidden_penalty.saturating_neg(),
+                );
+            }
+        }
+
+        // ++++ path depth penalty
+        if options.depth_penalty != 0 {
+            let depth: i32 = chars.iter().filter(|ch| is_path_separator(ch)).count() as i32;
+            adjust(
+                &mut result_1,
+                parts,
+                Component::Depth,
+                depth.saturating_mul(options.depth_penalty).saturating_neg(),
+            );
+        }
+
+        // ++++ extension boost
+        if let Some(ext) = extension {
+            if !options.extension_boosts.is_empty() {
+                let end: usize = segmentation
+                    .basepath
+                    .as_ref()
+                    .map_or(chars.len(), |b| b.end);
+                let name: String = chars[ext + 1..end].iter().collect();
+                if let Some(boost) = options.extension_boosts.get(&name) {
+                    adjust(&mut result_1, parts, Component::ExtensionBoost, *boost);
+                }
+            }
+        }
+
+        return result_1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use preset::Preset;
+
+    /// Linear congruential generator, so the generated inputs are the same
+    /// on every run.
+    struct Lcg(u64);
+
+    impl Lcg {
+        /// Return a number below N.
+        fn below(&mut self, n: u64) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            return (self.0 >> 33) % n;
+        }
+    }
+
+    /// Summarize MATCHES with every field the matchers compute.
+    fn summary(matches: &[Result]) -> Vec<(&[i32], Score, [i32; 4])> {
+        return matches
+            .iter()
+            .map(|m| {
+                let stats = [m.tail, m.longest_run, m.gap_count, m.first_match_index];
+                (m.indices.as_slice(), m.score, stats)
+            })
+            .collect();
+    }
+
+    #[test]
+    fn iterative_matches_recursive() {
+        let alphabet: Vec<char> = "aAbB_/.c-".chars().collect();
+        let mut rng: Lcg = Lcg(12345);
+        for _ in 0..5000 {
+            let len: usize = rng.below(30) as usize;
+            let str: String = (0..len)
+                .map(|_| alphabet[rng.below(alphabet.len() as u64) as usize])
+                .collect();
+            let query_length: usize = 1 + rng.below(5) as usize;
+            let query: String = (0..query_length)
+                .map(|_| alphabet[rng.below(6) as usize])
+                .collect();
+            let case_mode: CaseMode = match rng.below(3) {
+                0 => CaseMode::Mixed,
+                1 => CaseMode::Sensitive,
+                _ => CaseMode::Insensitive,
+            };
+            let query: String = if case_mode == CaseMode::Insensitive {
+                query.to_lowercase()
+            } else {
+                query
+            };
+            let max_gap: Option<u32> = match rng.below(3) {
+                0 => Some(rng.below(4) as u32),
+                _ => None,
+            };
+            let greater_than: Option<u32> = match rng.below(4) {
+                0 if 0 < len => Some(rng.below(len as u64) as u32),
+                _ => None,
+            };
+            let keep: usize = 1 + rng.below(4) as usize;
+
+            let prepared: Prepared = Prepared::new(&str, &ScoreOptions::default());
+            let str_info: StrInfo = prepared.str_info(case_mode);
+            let chars: Vec<char> = query.chars().collect();
+            let mut recursive: Vec<Result> = Vec::new();
+            find_best_match_gapped(
+                &mut recursive,
+                &str_info,
+                &prepared.heatmap,
+                greater_than,
+                &chars,
+                query_length as i32,
+                0,
+                &mut HashMap::new(),
+                max_gap,
+                keep,
+                CONTIGUOUS_BASE,
+            );
+            let mut iterative: Vec<Result> = Vec::new();
+            find_best_match_iterative(
+                &mut iterative,
+                &str_info,
+                &prepared.heatmap,
+                greater_than,
+                &query,
+                query_length as i32,
+                &mut HashMap::new(),
+                max_gap,
+                keep,
+                CONTIGUOUS_BASE,
+            );
+            assert_eq!(
+                summary(&recursive),
+                summary(&iterative),
+                "{:?} against {:?}",
+                query,
+                str
+            );
+        }
+    }
+
+    #[test]
+    fn baseline_scores() {
+        let cases: [(&str, &str, i64, &[i32]); 9] = [
+            ("switch-to-buffer", "stb", 237, &[0, 7, 10]),
+            ("src/search.rs", "a", -8, &[6]),
+            (
+                "third_party/vendor/foo/src/lib.rs",
+                "src",
+                160,
+                &[23, 24, 25],
+            ),
+            ("Cargo.toml", "a", -3, &[1]),
+            ("MyCamelCaseThing", "MCC", 243, &[0, 2, 7]),
+            ("a-b-c-d-e-f", "a", 79, &[0]),
+            ("std::collections::HashMap", "hm", 149, &[18, 22]),
+            ("find-file-other-window", "rd", -30, &[14, 19]),
+            ("abcabcabcabcabc/abc", "a", 83, &[0]),
+        ];
+        for (str, query, score_value, indices) in cases {
+            let result: Result = score(str, query).unwrap();
+            assert_eq!(
+                result.score,
+                Score(score_value),
+                "{:?} against {:?}",
+                query,
+                str
+            );
+            assert_eq!(result.indices, indices, "{:?} against {:?}", query, str);
+        }
+        assert!(score("switch-to-buffer", "xyz").is_none());
+    }
+
+    #[test]
+    fn score_many_reuses_buffers_faithfully() {
+        let candidates: [&str; 6] = [
+            "switch-to-buffer",
+            "~/src/lib.rs",
+            "",
+            "https://example.com/a_b",
+            "ab\u{200b}c",
+            "Cargo.toml",
+        ];
+        for text in ["sb", "ab", "c"] {
+            let query: Query = Query::new(text).preset(Preset::Url);
+            let many: Vec<Option<Result>> = score_many_query(&candidates, &query);
+            for (candidate, result) in candidates.iter().zip(many) {
+                let single: Option<Result> = score_query(candidate, &query);
+                assert_eq!(
+                    single.as_ref().map(|m| (&m.indices, m.score)),
+                    result.as_ref().map(|m| (&m.indices, m.score)),
+                    "{:?} against {:?}",
+                    text,
+                    candidate
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn n_best_starts_with_best_match() {
+        let cases: [(&str, &str, i64, &[i32]); 2] = [
+            (".ABAb.Aa-a.B", "BAB", 47, &[2, 6, 11]),
+            ("/-BB.baBB/-AA.", "bbB", 190, &[2, 7, 8]),
+        ];
+        for (str, query, score_value, indices) in cases {
+            let query: Query = Query::new(query);
+            let best: Result = score_query(str, &query).unwrap();
+            let n_best: Vec<Result> = score_n_best(str, &query, 3);
+            assert_eq!(best.score, Score(score_value), "{:?}", str);
+            assert_eq!(best.indices, indices, "{:?}", str);
+            assert_eq!(summary(&n_best[..1]), summary(&[best]), "{:?}", str);
+        }
+    }
 }
@@ -0,0 +1,158 @@
+/**
+ * $File: session.rs $
+ * $Date: 2026-10-16 01:17:57 $
+ * $Revision: $
+ * $Creator: Jen-Chieh Shen $
+ * $Notice: See LICENSE.txt for modification and distribution information
+ *                   Copyright © 2026 by Shen, Jen-Chieh $
+ */
+use candidate::Candidate;
+use query::Query;
+use rank::{sort_ranked, Page};
+use search::{Result, ScoreOptions};
+
+/// A fixed candidate set ranked against a query as it is typed.
+///
+/// When the new query extends the previous one, only the candidates that
+/// matched before can match again, so `refine` rescores just those.
+/// Anything else, like deleting a character or changing an option, starts
+/// over from the whole set.
+#[derive(Debug, Clone)]
+pub struct Session {
+    candidates: Vec<Candidate>,
+    query: Option<Query>,
+    ranked: Vec<(usize, Result)>,
+}
+
+impl Session {
+    /// Create a session over CANDIDATES, prepared with the default options.
+    pub fn new(candidates: &[&str]) -> Session {
+        return Session::with_options(candidates, &ScoreOptions::default());
+    }
+
+    /// Create a session over CANDIDATES, prepared with OPTIONS.
+    ///
+    /// Queries should carry the same options to benefit from the
+    /// preparation.
+    pub fn with_options(candidates: &[&str], options: &ScoreOptions) -> Session {
+        return Session {
+            candidates: candidates
+                .iter()
+                .map(|candidate| Candidate::with_options(candidate, options))
+                .collect(),
+            query: None,
+            ranked: Vec::new(),
+        };
+    }
+
+    /// Return the candidates of the session.
+    pub fn candidates(&self) -> &[Candidate] {
+        return &self.candidates;
+    }
+
+    /// Return the query of the last `refine`, if any.
+    pub fn query(&self) -> Option<&Query> {
+        return self.query.as_ref();
+    }
+
+    /// Return the ranking of the last `refine`.
+    pub fn results(&self) -> &[(usize, Result)] {
+        return &self.ranked;
+    }
+
+    /// Rank the candidates against QUERY.
+    ///
+    /// Returns the index and result of each matching candidate, sorted by
+    /// descending score; equal scores are ordered by the query's
+    /// `Tiebreak`. The ranking is the same whether or not the previous one
+    /// could be reused.
+    pub fn refine(&mut self, query: &Query) -> &[(usize, Result)] {
+        let survivors: Vec<usize> = match &self.query {
+            Some(previous) if extends(previous, query) => {
+                let mut survivors: Vec<usize> = self.ranked.iter().map(|item| item.0).collect();
+                survivors.sort_unstable();
+                survivors
+            }
+            _ => (0..self.candidates.len()).collect(),
+        };
+        let mut ranked: Vec<(usize, Result)> = survivors
+            .into_iter()
+            .filter_map(|index| {
+                self.candidates[index]
+                    .score(query)
+                    .map(|result| (index, result))
+            })
+            .collect();
+        let candidates: &[Candidate] = &self.candidates;
+        sort_ranked(&mut ranked, query.tiebreak, |index| {
+            candidates[index].text()
+        });
+        self.ranked = ranked;
+        self.query = Some(query.clone());
+        return &self.ranked;
+    }
+
+    /// Return the page of the current ranking that starts at OFFSET and
+    /// holds at most LIMIT results, with the number of matches.
+    pub fn page(&self, offset: usize, limit: usize) -> Page<usize> {
+        let start: usize = offset.min(self.ranked.len());
+        let end: usize = start.saturating_add(limit).min(self.ranked.len());
+        return Page {
+            items: self.ranked[start..end].to_vec(),
+            total: self.ranked.len(),
+        };
+    }
+}
+
+/// Check if every match of QUERY is also a match of PREVIOUS, because
+/// QUERY only appends characters to it.
+fn extends(previous: &Query, query: &Query) -> bool {
+    // An empty query matches nothing, so it has no survivors to narrow
+    return !previous.text.is_empty()
+        && query.text.starts_with(&previous.text)
+        && query.options == previous.options
+        && query.case_mode == previous.case_mode
+        && query.word_starts_only == previous.word_starts_only
+        && query.max_gap == previous.max_gap;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use query::Tiebreak;
+    use rank::rank_items_query;
+
+    /// Reduce RANKED to the index, indices and score of each match.
+    fn summary(ranked: &[(usize, Result)]) -> Vec<(usize, &[i32], i64)> {
+        return ranked
+            .iter()
+            .map(|(index, result)| (*index, result.indices.as_slice(), result.score.0))
+            .collect();
+    }
+
+    #[test]
+    fn refine_matches_ranking_from_scratch() {
+        let candidates: [&str; 8] = [
+            "src/search.rs",
+            "src/session.rs",
+            "src/rank.rs",
+            "README.md",
+            "src/sr.rs",
+            "tests/search_test.rs",
+            "src/search.rs",
+            "scripts/release.sh",
+        ];
+        let items: Vec<(usize, &str)> = candidates.iter().cloned().enumerate().collect();
+        // Grow the query, delete a character, then grow it again
+        let texts: [&str; 9] = ["", "s", "sr", "src", "srcs", "src", "srr", "srrs", "S"];
+        for tiebreak in [Tiebreak::Input, Tiebreak::Natural] {
+            let mut session: Session = Session::new(&candidates);
+            for text in texts {
+                let query: Query = Query::new(text).tiebreak(tiebreak);
+                let expected: Vec<(usize, Result)> = rank_items_query(&items, &query);
+                let refined: &[(usize, Result)] = session.refine(&query);
+                assert_eq!(summary(refined), summary(&expected), "{:?}", text);
+            }
+        }
+    }
+}
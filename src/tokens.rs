@@ -0,0 +1,62 @@
+/**
+ * $File: tokens.rs $
+ * $Date: 2026-10-16 00:54:54 $
+ * $Revision: $
+ * $Creator: Jen-Chieh Shen $
+ * $Notice: See LICENSE.txt for modification and distribution information
+ *                   Copyright © 2026 by Shen, Jen-Chieh $
+ */
+use query::Query;
+use search::{score_query, Result, ScoreOptions};
+
+/// Character joining the tokens, the only word separator while they are
+/// matched.
+const TOKEN_SEPARATOR: char = '\u{1f}';
+
+/// Result of scoring a token sequence.
+#[derive(Debug, Clone)]
+pub struct TokenResult {
+    /// Matched characters as (token index, char offset in the token)
+    /// pairs.
+    pub positions: Vec<(usize, usize)>,
+    /// Match against the tokens joined by U+001F, one char between each.
+    pub result: Result,
+}
+
+/// Score the token sequence TOKENS, like command words or tags, against
+/// QUERY.
+///
+/// Words start at token edges only, so separator characters inside a
+/// token, like `-` in `utf-8`, don't split it.
+pub fn score_tokens(tokens: &[&str], query: &str) -> Option<TokenResult> {
+    return score_tokens_query(tokens, &Query::new(query));
+}
+
+/// Score the token sequence TOKENS against the compiled QUERY, like
+/// `score_tokens`. The word separators of its options are replaced.
+pub fn score_tokens_query(tokens: &[&str], query: &Query) -> Option<TokenResult> {
+    let joined: String = tokens.join(&TOKEN_SEPARATOR.to_string());
+    let options: ScoreOptions = ScoreOptions {
+        word_separators: vec![TOKEN_SEPARATOR],
+        ..query.options.clone()
+    };
+    let result: Result = score_query(&joined, &query.clone().options(options))?;
+
+    // Character index where each token starts in the joined string
+    let mut starts: Vec<usize> = Vec::with_capacity(tokens.len());
+    let mut start: usize = 0;
+    for token in tokens {
+        starts.push(start);
+        start += token.chars().count() + 1;
+    }
+    let positions: Vec<(usize, usize)> = result
+        .indices
+        .iter()
+        .map(|&index| {
+            let index: usize = index as usize;
+            let token: usize = starts.partition_point(|&start| start <= index) - 1;
+            (token, index - starts[token])
+        })
+        .collect();
+    return Some(TokenResult { positions, result });
+}
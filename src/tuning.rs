@@ -0,0 +1,312 @@
+/**
+ * $File: tuning.rs $
+ * $Date: 2026-10-16 00:53:50 $
+ * $Revision: $
+ * $Creator: Jen-Chieh Shen $
+ * $Notice: See LICENSE.txt for modification and distribution information
+ *                   Copyright © 2026 by Shen, Jen-Chieh $
+ */
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+use query::Query;
+use search::{score_query, ScoreOptions};
+use value::Score;
+
+/// Number of leading results compared by `QueryDiff::top_overlap`.
+const TOP_COUNT: usize = 10;
+
+/// Number of flipped pairs kept as examples per query.
+const FLIP_EXAMPLES: usize = 5;
+
+/// How the ranking of one query changes between two options.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryDiff {
+    /// The query.
+    pub query: String,
+    /// Kendall tau-b between the two rankings, over the candidates both
+    /// match: 1 when they agree on every pair, -1 when they disagree on
+    /// every pair. Ties are allowed for.
+    pub kendall_tau: f32,
+    /// How many of the first ten results the rankings share.
+    pub top_overlap: usize,
+    /// Number of pairs the two options order the other way round.
+    pub flipped_count: usize,
+    /// Up to five flipped pairs, the one ranked higher by the first
+    /// options first.
+    pub flipped: Vec<(String, String)>,
+}
+
+/// How the rankings of a corpus change between two options.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionsDiff {
+    /// One entry per query, in query order.
+    pub queries: Vec<QueryDiff>,
+    /// Mean `QueryDiff::kendall_tau` over the queries.
+    pub mean_kendall_tau: f32,
+    /// Mean `QueryDiff::top_overlap` over the queries.
+    pub mean_top_overlap: f32,
+}
+
+/// Rank CANDIDATES for every one of QUERIES under both A and B, and
+/// report how the rankings differ.
+///
+/// Comparing every pair of candidates is quadratic, so this is meant for
+/// tuning corpora rather than live candidate lists.
+///
+/// # Arguments
+///
+/// * `candidates` - The candidate strings.
+/// * `queries` - The queries to rank the candidates for.
+/// * `a` - The current options.
+/// * `b` - The options to compare them with.
+pub fn compare_options(
+    candidates: &[&str],
+    queries: &[&str],
+    a: &ScoreOptions,
+    b: &ScoreOptions,
+) -> OptionsDiff {
+    let diffs: Vec<QueryDiff> = queries
+        .iter()
+        .map(|query| compare_query(candidates, query, a, b))
+        .collect();
+    let count: f32 = diffs.len().max(1) as f32;
+    let mean_kendall_tau: f32 = diffs.iter().map(|diff| diff.kendall_tau).sum::<f32>() / count;
+    let mean_top_overlap: f32 =
+        diffs.iter().map(|diff| diff.top_overlap).sum::<usize>() as f32 / count;
+    return OptionsDiff {
+        queries: diffs,
+        mean_kendall_tau,
+        mean_top_overlap,
+    };
+}
+
+/// Compare the rankings of CANDIDATES for QUERY under A and B.
+fn compare_query(
+    candidates: &[&str],
+    query: &str,
+    a: &ScoreOptions,
+    b: &ScoreOptions,
+) -> QueryDiff {
+    let scores = |options: &ScoreOptions| -> Vec<Option<Score>> {
+        let query: Query = Query::new(query).options(options.clone());
+        return candidates
+            .iter()
+            .map(|candidate| score_query(candidate, &query).map(|result| result.score))
+            .collect();
+    };
+    let scores_a: Vec<Option<Score>> = scores(a);
+    let scores_b: Vec<Option<Score>> = scores(b);
+
+    // Pairs only count when both options match both candidates
+    let both: Vec<(usize, Score, Score)> = scores_a
+        .iter()
+        .zip(&scores_b)
+        .enumerate()
+        .filter_map(|(index, pair)| match pair {
+            (Some(a), Some(b)) => Some((index, *a, *b)),
+            _ => None,
+        })
+        .collect();
+    let mut concordant: usize = 0;
+    let mut discordant: usize = 0;
+    let mut ties_a: usize = 0;
+    let mut ties_b: usize = 0;
+    let mut flipped: Vec<(String, String)> = Vec::new();
+    for (i, x) in both.iter().enumerate() {
+        for y in &both[i + 1..] {
+            let order_a = x.1.cmp(&y.1);
+            let order_b = x.2.cmp(&y.2);
+            if order_a.is_eq() || order_b.is_eq() {
+                ties_a += order_a.is_eq() as usize;
+                ties_b += order_b.is_eq() as usize;
+                continue;
+            }
+            if order_a == order_b {
+                concordant += 1;
+                continue;
+            }
+            discordant += 1;
+            if flipped.len() < FLIP_EXAMPLES {
+                let (higher, lower) = if order_a.is_gt() {
+                    (x.0, y.0)
+                } else {
+                    (y.0, x.0)
+                };
+                flipped.push((
+                    candidates[higher].to_string(),
+                    candidates[lower].to_string(),
+                ));
+            }
+        }
+    }
+    let pairs: usize = both.len() * both.len().saturating_sub(1) / 2;
+    let untied: f32 = ((pairs - ties_a) as f32 * (pairs - ties_b) as f32).sqrt();
+    let kendall_tau: f32 = if untied == 0.0 {
+        1.0
+    } else {
+        (concordant as f32 - discordant as f32) / untied
+    };
+
+    let top_a: HashSet<usize> = top(&scores_a);
+    let top_overlap: usize = top(&scores_b).intersection(&top_a).count();
+    return QueryDiff {
+        query: query.to_string(),
+        kendall_tau,
+        top_overlap,
+        flipped_count: discordant,
+        flipped,
+    };
+}
+
+/// Return the indices of the first `TOP_COUNT` matches in SCORES, ranked
+/// like `rank_items`.
+fn top(scores: &[Option<Score>]) -> HashSet<usize> {
+    let mut ranked: Vec<(usize, Score)> = scores
+        .iter()
+        .enumerate()
+        .filter_map(|(index, score)| score.map(|score| (index, score)))
+        .collect();
+    ranked.sort_by_key(|item| Reverse(item.1));
+    return ranked.iter().take(TOP_COUNT).map(|item| item.0).collect();
+}
+
+/// A numeric field of `ScoreOptions` that `tune` may change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tunable {
+    /// `ScoreOptions::default_score`.
+    DefaultScore,
+    /// `ScoreOptions::word_start_bonus`.
+    WordStartBonus,
+    /// `ScoreOptions::contiguous_base`.
+    ContiguousBase,
+    /// `ScoreOptions::basepath_bonus`.
+    BasepathBonus,
+    /// `ScoreOptions::basepath_word_penalty`.
+    BasepathWordPenalty,
+    /// `ScoreOptions::extension_penalty`.
+    ExtensionPenalty,
+    /// `ScoreOptions::hidden_penalty`.
+    HiddenPenalty,
+    /// `ScoreOptions::depth_penalty`.
+    DepthPenalty,
+    /// `ScoreOptions::prefix_bonus`.
+    PrefixBonus,
+    /// `ScoreOptions::initialism_bonus`.
+    InitialismBonus,
+    /// `ScoreOptions::component_initials_bonus`.
+    ComponentInitialsBonus,
+}
+
+impl Tunable {
+    /// Set this field of OPTIONS to VALUE.
+    pub fn set(self, options: &mut ScoreOptions, value: i32) {
+        let field: &mut i32 = match self {
+            Tunable::DefaultScore => &mut options.default_score,
+            Tunable::WordStartBonus => &mut options.word_start_bonus,
+            Tunable::ContiguousBase => &mut options.contiguous_base,
+            Tunable::BasepathBonus => &mut options.basepath_bonus,
+            Tunable::BasepathWordPenalty => &mut options.basepath_word_penalty,
+            Tunable::ExtensionPenalty => &mut options.extension_penalty,
+            Tunable::HiddenPenalty => &mut options.hidden_penalty,
+            Tunable::DepthPenalty => &mut options.depth_penalty,
+            Tunable::PrefixBonus => &mut options.prefix_bonus,
+            Tunable::InitialismBonus => &mut options.initialism_bonus,
+            Tunable::ComponentInitialsBonus => &mut options.component_initials_bonus,
+        };
+        *field = value;
+    }
+}
+
+/// A labeled example: for `query`, `winner` should rank above `loser`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Preference {
+    /// The query both candidates are matched against.
+    pub query: String,
+    /// The candidate that should rank higher.
+    pub winner: String,
+    /// The candidate that should rank lower.
+    pub loser: String,
+}
+
+impl Preference {
+    /// Create a preference of WINNER over LOSER for QUERY.
+    pub fn new(query: &str, winner: &str, loser: &str) -> Preference {
+        return Preference {
+            query: query.to_string(),
+            winner: winner.to_string(),
+            loser: loser.to_string(),
+        };
+    }
+
+    /// Check if OPTIONS rank the winner strictly above the loser.
+    pub fn holds(&self, options: &ScoreOptions) -> bool {
+        let query: Query = Query::new(&self.query).options(options.clone());
+        let winner: Option<Score> = score_query(&self.winner, &query).map(|result| result.score);
+        let loser: Option<Score> = score_query(&self.loser, &query).map(|result| result.score);
+        return winner.is_some() && loser < winner;
+    }
+}
+
+/// The outcome of `tune`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tuned {
+    /// The best options found.
+    pub options: ScoreOptions,
+    /// Number of preferences those options still get wrong.
+    pub violations: usize,
+}
+
+/// Search for options that get as few of PREFERENCES wrong as possible.
+///
+/// Starting from BASE, each field in GRID is set in turn to the value
+/// among its candidates with the fewest violations, keeping the others
+/// fixed; rounds repeat until no field changes or ROUNDS is reached. Ties
+/// keep the current value, so BASE wins unless something beats it.
+///
+/// # Arguments
+///
+/// * `preferences` - The labeled examples.
+/// * `base` - The options to start from.
+/// * `grid` - The fields to tune, with the values to try for each.
+/// * `rounds` - Maximum number of passes over GRID.
+pub fn tune(
+    preferences: &[Preference],
+    base: &ScoreOptions,
+    grid: &[(Tunable, Vec<i32>)],
+    rounds: usize,
+) -> Tuned {
+    let violations = |options: &ScoreOptions| -> usize {
+        return preferences
+            .iter()
+            .filter(|preference| !preference.holds(options))
+            .count();
+    };
+    let mut best: ScoreOptions = base.clone();
+    let mut best_violations: usize = violations(&best);
+    for _ in 0..rounds {
+        let mut changed: bool = false;
+        for (tunable, values) in grid {
+            for &value in values {
+                let mut options: ScoreOptions = best.clone();
+                tunable.set(&mut options, value);
+                if options == best {
+                    continue;
+                }
+                let count: usize = violations(&options);
+                if count < best_violations {
+                    best = options;
+                    best_violations = count;
+                    changed = true;
+                }
+            }
+        }
+        if !changed || best_violations == 0 {
+            break;
+        }
+    }
+    return Tuned {
+        options: best,
+        violations: best_violations,
+    };
+}
@@ -0,0 +1,115 @@
+/**
+ * $File: value.rs $
+ * $Date: 2026-10-16 00:34:54 $
+ * $Revision: $
+ * $Creator: Jen-Chieh Shen $
+ * $Notice: See LICENSE.txt for modification and distribution information
+ *                   Copyright © 2026 by Shen, Jen-Chieh $
+ */
+use std::fmt;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+
+/// Score of a match; higher is better.
+///
+/// Scores are 64-bit, so penalties piling up on long strings stay far from
+/// the bounds, and arithmetic saturates instead of wrapping anyway.
+/// `Score::MIN` is reserved as the sentinel for "no match".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Score(pub i64);
+
+impl Score {
+    /// Sentinel below every real score.
+    pub const MIN: Score = Score(i64::MIN);
+    /// Largest representable score.
+    pub const MAX: Score = Score(i64::MAX);
+
+    pub fn new(value: i64) -> Score {
+        Score(value)
+    }
+
+    /// Return the raw score.
+    pub fn get(self) -> i64 {
+        return self.0;
+    }
+
+    /// Return true unless this is the `Score::MIN` sentinel.
+    pub fn is_match(self) -> bool {
+        return self != Score::MIN;
+    }
+
+    pub fn saturating_add(self, rhs: Score) -> Score {
+        return Score(self.0.saturating_add(rhs.0));
+    }
+
+    pub fn saturating_sub(self, rhs: Score) -> Score {
+        return Score(self.0.saturating_sub(rhs.0));
+    }
+
+    /// Map the score linearly into `0.0..=1.0`, where MIN and MAX are the
+    /// lowest and highest scores being compared.
+    ///
+    /// Only compare scores produced with the same options; scores from
+    /// different configurations don't share a scale.
+    pub fn normalized(self, min: Score, max: Score) -> f32 {
+        if !self.is_match() {
+            return 0.0;
+        }
+        if max <= min {
+            return 1.0;
+        }
+        let ratio: f64 = (self.0 as f64 - min.0 as f64) / (max.0 as f64 - min.0 as f64);
+        return ratio.clamp(0.0, 1.0) as f32;
+    }
+}
+
+impl Add<i32> for Score {
+    type Output = Score;
+
+    fn add(self, rhs: i32) -> Score {
+        return Score(self.0.saturating_add(rhs as i64));
+    }
+}
+
+impl Sub<i32> for Score {
+    type Output = Score;
+
+    fn sub(self, rhs: i32) -> Score {
+        return Score(self.0.saturating_sub(rhs as i64));
+    }
+}
+
+impl AddAssign<i32> for Score {
+    fn add_assign(&mut self, rhs: i32) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign<i32> for Score {
+    fn sub_assign(&mut self, rhs: i32) {
+        *self = *self - rhs;
+    }
+}
+
+impl From<i32> for Score {
+    fn from(value: i32) -> Score {
+        Score(value as i64)
+    }
+}
+
+impl From<i64> for Score {
+    fn from(value: i64) -> Score {
+        Score(value)
+    }
+}
+
+impl From<Score> for i64 {
+    fn from(score: Score) -> i64 {
+        return score.0;
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", self.0);
+    }
+}